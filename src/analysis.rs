@@ -0,0 +1,166 @@
+use {
+    std::collections::{HashMap, HashSet},
+    crate::{
+        element::*,
+        rule::RuleId,
+    },
+};
+
+pub(crate) type RuleMap = HashMap<RuleId, Element>;
+
+pub(crate) fn nullable_rules(rule_map: &RuleMap) -> HashSet<RuleId> {
+    let mut nullable = HashSet::new();
+
+    loop {
+        let mut changed = false;
+
+        for (id, elem) in rule_map {
+            if !nullable.contains(id) && elem.is_nullable(&nullable) {
+                nullable.insert(id.clone());
+                changed = true;
+            }
+        }
+
+        if !changed {
+            return nullable;
+        }
+    }
+}
+
+// Rules which can be called at the same input index as the given rule.
+pub(crate) fn leftmost_graph(rule_map: &RuleMap, nullable: &HashSet<RuleId>) -> HashMap<RuleId, Vec<RuleId>> {
+    let mut graph = HashMap::new();
+
+    for (id, elem) in rule_map {
+        let mut callees = Vec::new();
+        elem.leftmost_rule_ids(nullable, &mut callees);
+        callees.retain(|callee| rule_map.contains_key(callee));
+        graph.insert(id.clone(), callees);
+    }
+
+    graph
+}
+
+// Strongly connected components which contain a left recursive cycle.
+pub(crate) fn left_recursive_cycles(graph: &HashMap<RuleId, Vec<RuleId>>) -> Vec<Vec<RuleId>> {
+    let mut ids: Vec<&RuleId> = graph.keys().collect();
+    ids.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut tarjan = Tarjan {
+        graph,
+        index: 0,
+        indexes: HashMap::new(),
+        lowlinks: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+
+    for each_id in ids {
+        if !tarjan.indexes.contains_key(each_id) {
+            tarjan.connect(each_id);
+        }
+    }
+
+    tarjan.components.into_iter().filter(|component| {
+        component.len() > 1 || graph[&component[0]].contains(&component[0])
+    }).map(|mut component| {
+        component.sort_by(|a, b| a.0.cmp(&b.0));
+        component
+    }).collect()
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct LeftRecursion {
+    // Rules which grow a seed. At least one rule of each cycle is chosen.
+    pub(crate) leaders: HashSet<RuleId>,
+    // Rules belonging to a cycle, which must not be memoized except leaders.
+    pub(crate) members: HashSet<RuleId>,
+}
+
+impl LeftRecursion {
+    pub(crate) fn detect(rule_map: &RuleMap) -> LeftRecursion {
+        let nullable = nullable_rules(rule_map);
+        let graph = leftmost_graph(rule_map, &nullable);
+        let mut left_recursion = LeftRecursion::default();
+
+        for each_cycle in left_recursive_cycles(&graph) {
+            left_recursion.members.extend(each_cycle.iter().cloned());
+            left_recursion.choose_leaders(&graph, each_cycle);
+        }
+
+        left_recursion
+    }
+
+    fn choose_leaders(&mut self, graph: &HashMap<RuleId, Vec<RuleId>>, cycle: Vec<RuleId>) {
+        let leader = cycle[0].clone();
+        let remaining: HashSet<&RuleId> = cycle.iter().filter(|id| **id != leader).collect();
+
+        // Cut the leader off and choose leaders again from the cycles still remaining.
+        let subgraph = remaining.iter().map(|id| {
+            let callees = graph[*id].iter().filter(|callee| remaining.contains(callee)).cloned().collect();
+            ((*id).clone(), callees)
+        }).collect();
+
+        self.leaders.insert(leader);
+
+        for each_cycle in left_recursive_cycles(&subgraph) {
+            self.choose_leaders(&subgraph, each_cycle);
+        }
+    }
+
+    pub(crate) fn is_leader(&self, id: &RuleId) -> bool {
+        self.leaders.contains(id)
+    }
+
+    pub(crate) fn is_member(&self, id: &RuleId) -> bool {
+        self.members.contains(id)
+    }
+}
+
+struct Tarjan<'a> {
+    graph: &'a HashMap<RuleId, Vec<RuleId>>,
+    index: usize,
+    indexes: HashMap<RuleId, usize>,
+    lowlinks: HashMap<RuleId, usize>,
+    stack: Vec<RuleId>,
+    on_stack: HashSet<RuleId>,
+    components: Vec<Vec<RuleId>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn connect(&mut self, id: &RuleId) {
+        self.indexes.insert(id.clone(), self.index);
+        self.lowlinks.insert(id.clone(), self.index);
+        self.index += 1;
+        self.stack.push(id.clone());
+        self.on_stack.insert(id.clone());
+
+        for each_callee in &self.graph[id] {
+            if !self.indexes.contains_key(each_callee) {
+                self.connect(each_callee);
+                let lowlink = self.lowlinks[id].min(self.lowlinks[each_callee]);
+                self.lowlinks.insert(id.clone(), lowlink);
+            } else if self.on_stack.contains(each_callee) {
+                let lowlink = self.lowlinks[id].min(self.indexes[each_callee]);
+                self.lowlinks.insert(id.clone(), lowlink);
+            }
+        }
+
+        if self.lowlinks[id] == self.indexes[id] {
+            let mut component = Vec::new();
+
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(&member);
+                let is_root = member == *id;
+                component.push(member);
+
+                if is_root {
+                    break;
+                }
+            }
+
+            self.components.push(component);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use {
+    std::fmt::Write,
+    crate::{
+        parser::ParserError,
+        tree::*,
+        visitor::*,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    // None if the error isn't related to any input position.
+    pub span: Option<Span>,
+}
+
+impl From<&SyntaxError> for Diagnostic {
+    fn from(error: &SyntaxError) -> Diagnostic {
+        Diagnostic::new(error.message.clone(), Some(error.span.clone()))
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Diagnostic {
+        match error {
+            // The position is omitted from the message since it is shown by the span.
+            ParserError::UnexpectedInput { position, expected } => {
+                let mut message = "unexpected input".to_string();
+
+                if !expected.is_empty() {
+                    let expected = expected.iter().map(|expr| expr.to_string()).collect::<Vec<String>>();
+                    message += &format!(", expected {}", expected.join(", "));
+                }
+
+                Diagnostic::new(message, Some(Span::new(position.clone(), position.clone())))
+            },
+            _ => Diagnostic::new(error.to_string(), None),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn new(message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            message,
+            span,
+        }
+    }
+
+    pub fn render(&self, source: &str) -> String {
+        self.render_with_style(source, &PLAIN_STYLE)
+    }
+
+    pub fn render_colored(&self, source: &str) -> String {
+        self.render_with_style(source, &ANSI_STYLE)
+    }
+
+    fn render_with_style(&self, source: &str, style: &Style) -> String {
+        let mut s = format!("{}error{}: {}{}{}\n", style.error, style.reset, style.emphasis, self.message, style.reset);
+
+        let span = match &self.span {
+            Some(v) => v,
+            None => return s,
+        };
+
+        let line = source.split('\n').nth(span.start.line).unwrap_or("").trim_end_matches('\r');
+        let line_len = line.chars().count();
+        let start = span.start.column.min(line_len);
+        // Multiline spans are underlined until the end of the first line.
+        let end = if span.end.line == span.start.line { span.end.column.min(line_len) } else { line_len };
+        let underline = "^".repeat(end.saturating_sub(start).max(1));
+        // Keep tabs so that the underline is aligned with the source line.
+        let padding = line.chars().take(start).map(|ch| if ch == '\t' { '\t' } else { ' ' }).collect::<String>();
+
+        let line_number = (span.start.line + 1).to_string();
+        let gutter = " ".repeat(line_number.len());
+
+        let _ = writeln!(s, "{}{}-->{} {}:{}", gutter, style.gutter, style.reset, line_number, span.start.column + 1);
+        let _ = writeln!(s, "{} {}|{}", gutter, style.gutter, style.reset);
+        let _ = writeln!(s, "{}{} |{} {}", style.gutter, line_number, style.reset, line);
+        let _ = writeln!(s, "{} {}|{} {}{}{}{}", gutter, style.gutter, style.reset, padding, style.error, underline, style.reset);
+        s
+    }
+}
+
+struct Style {
+    error: &'static str,
+    emphasis: &'static str,
+    gutter: &'static str,
+    reset: &'static str,
+}
+
+const PLAIN_STYLE: Style = Style {
+    error: "",
+    emphasis: "",
+    gutter: "",
+    reset: "",
+};
+
+const ANSI_STYLE: Style = Style {
+    error: "\x1b[1;31m",
+    emphasis: "\x1b[1m",
+    gutter: "\x1b[1;34m",
+    reset: "\x1b[0m",
+};
+
+impl SyntaxTree {
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut collector = DiagnosticCollector(Vec::new());
+        self.walk(&mut collector);
+        collector.0
+    }
+}
+
+struct DiagnosticCollector(Vec<Diagnostic>);
+
+impl SyntaxVisitor for DiagnosticCollector {
+    fn visit_error(&mut self, error: &SyntaxError) -> VisitControl {
+        self.0.push(Diagnostic::from(error));
+        VisitControl::Continue
+    }
+}
@@ -1,11 +1,18 @@
 use {
-    std::fmt::{self, Display, Formatter},
+    std::collections::HashSet,
+    std::fmt::{self, Debug, Display, Formatter},
+    std::sync::Arc,
     regex::Regex,
     crate::*,
+    crate::grammar::{GrammarError, GrammarResult},
     crate::rule::RuleId,
-    crate::tree::SyntaxChild,
+    crate::tree::{SyntaxChild, SyntaxChildVec},
 };
 
+pub type Reducer = Arc<dyn Fn(Vec<SyntaxChild>) -> Vec<SyntaxChild> + Send + Sync>;
+
+pub type Predicate = Arc<dyn Fn(&Vec<SyntaxChild>) -> bool + Send + Sync>;
+
 #[derive(Clone)]
 pub enum Element {
     Expression(Expression),
@@ -17,16 +24,27 @@ pub enum Element {
     Error(Box<Element>, String),
     Catch(Box<Element>, String),
     CatchSkip(Box<Element>, String, Box<Element>),
-    TreeReduction(Box<Element>, fn(Vec<SyntaxChild>) -> Vec<SyntaxChild>),
+    TreeReduction(Box<Element>, Reducer),
+    // Fails when the predicate rejects generated children.
+    Predicate(Box<Element>, Predicate),
     Group(Box<Element>, String),
     Expansion(Box<Element>),
     ExpansionOnce(Box<Element>),
     Join(Box<Element>),
     Hidden(Box<Element>),
+    Lexical(Box<Element>),
+    Precedence(Box<Element>, Vec<PrecedenceLevel>),
+    // Commits to the current alternative of the enclosing choice.
+    Cut,
+    // Raises a failure label recovered by the recovery element of `Volt`.
+    Throw(String),
+    // Captures matched input under the name in the current rule.
+    Capture(Box<Element>, String),
+    Indentation(Indentation),
 }
 
 impl Element {
-    fn range(self, range: LoopRange) -> Element {
+    pub(crate) fn range(self, range: LoopRange) -> Element {
         Element::Loop(Box::new(self), range)
     }
 
@@ -70,8 +88,21 @@ impl Element {
         Element::CatchSkip(Box::new(self), message.to_string(), Box::new(to))
     }
 
-    pub fn reduce(self, reducer: fn(Vec<SyntaxChild>) -> Vec<SyntaxChild>) -> Element {
-        Element::TreeReduction(Box::new(self), reducer)
+    pub fn reduce(self, reducer: impl Fn(Vec<SyntaxChild>) -> Vec<SyntaxChild> + Send + Sync + 'static) -> Element {
+        Element::TreeReduction(Box::new(self), Arc::new(reducer))
+    }
+
+    pub fn capture(self, name: &str) -> Element {
+        Element::Capture(Box::new(self), name.to_string())
+    }
+
+    pub fn check(self, predicate: impl Fn(&Vec<SyntaxChild>) -> bool + Send + Sync + 'static) -> Element {
+        Element::Predicate(Box::new(self), Arc::new(predicate))
+    }
+
+    // The predicate receives the joined text of generated children.
+    pub fn check_text(self, predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Element {
+        self.check(move |children| predicate(&children.join_into_string()))
     }
 
     pub fn group(self, name: &str) -> Element {
@@ -94,6 +125,10 @@ impl Element {
         Element::Hidden(Box::new(self))
     }
 
+    pub fn lexical(self) -> Element {
+        Element::Lexical(Box::new(self))
+    }
+
     pub fn around(self, enclosure: Element) -> Element {
         seq![enclosure.clone(), self, enclosure]
     }
@@ -110,9 +145,11 @@ impl Element {
         seq![separator.clone().optional(), self.clone(), seq![separator.clone(), self].min(0), separator.optional()]
     }
 
+    // Left recursion is supported by the parser. This only checks the leftmost element of direct self-reference.
+    #[deprecated(note = "left recursion is supported by the parser, so rules don't need to be checked")]
     pub fn has_left_recursion(&self, rule_id: &RuleId) -> bool {
         match self {
-            Element::Choice(elems) | Element::Sequence(elems) => match elems.get(0) {
+            Element::Choice(elems) | Element::Sequence(elems) => match elems.first() {
                 Some(first_elem) => first_elem.has_left_recursion(rule_id),
                 None => false,
             },
@@ -123,6 +160,122 @@ impl Element {
             _ => false,
         }
     }
+
+    pub(crate) fn is_nullable(&self, nullable_rules: &HashSet<RuleId>) -> bool {
+        match self {
+            Element::Expression(expr) => match expr {
+                Expression::Rule(id) => nullable_rules.contains(id),
+                // Captured input can be empty.
+                Expression::Backreference(_) => true,
+                _ => false,
+            },
+            Element::Choice(elems) => elems.iter().any(|e| e.is_nullable(nullable_rules)),
+            Element::Sequence(elems) => elems.iter().all(|e| e.is_nullable(nullable_rules)),
+            Element::Loop(elem, range) => range.min == 0 || elem.is_nullable(nullable_rules),
+            Element::PositiveLookahead(_) | Element::NegativeLookahead(_) | Element::Error(_, _) | Element::Catch(_, _) | Element::Cut | Element::Throw(_) |
+                Element::Indentation(_) => true,
+            Element::CatchSkip(elem, _, to) => elem.is_nullable(nullable_rules) || to.is_nullable(nullable_rules),
+            Element::TreeReduction(elem, _) | Element::Predicate(elem, _) | Element::Group(elem, _) | Element::Expansion(elem) | Element::ExpansionOnce(elem) |
+                Element::Join(elem) | Element::Hidden(elem) | Element::Lexical(elem) | Element::Precedence(elem, _) | Element::Capture(elem, _) => elem.is_nullable(nullable_rules),
+        }
+    }
+
+    // Trivia is skipped between loop iterations only when an iteration is a rule or a sequence, so that `[0-9]+` doesn't accept "1 2".
+    pub(crate) fn skips_trivia_between_iterations(&self) -> bool {
+        match self {
+            Element::Expression(Expression::Rule(_)) | Element::Sequence(_) | Element::Precedence(_, _) => true,
+            Element::Choice(elems) => elems.iter().any(|e| e.skips_trivia_between_iterations()),
+            Element::Loop(elem, _) | Element::Error(elem, _) | Element::Catch(elem, _) | Element::CatchSkip(elem, _, _) | Element::TreeReduction(elem, _) |
+                Element::Predicate(elem, _) | Element::Group(elem, _) | Element::Expansion(elem) | Element::ExpansionOnce(elem) | Element::Join(elem) |
+                Element::Hidden(elem) | Element::Capture(elem, _) => elem.skips_trivia_between_iterations(),
+            _ => false,
+        }
+    }
+
+    // Collects rule IDs which can be called before any input is consumed.
+    pub(crate) fn leftmost_rule_ids(&self, nullable_rules: &HashSet<RuleId>, ids: &mut Vec<RuleId>) {
+        match self {
+            Element::Expression(expr) => if let Expression::Rule(id) = expr {
+                if !ids.contains(id) {
+                    ids.push(id.clone());
+                }
+            },
+            Element::Choice(elems) => for each_elem in elems {
+                each_elem.leftmost_rule_ids(nullable_rules, ids);
+            },
+            Element::Sequence(elems) => for each_elem in elems {
+                each_elem.leftmost_rule_ids(nullable_rules, ids);
+
+                if !each_elem.is_nullable(nullable_rules) {
+                    break;
+                }
+            },
+            Element::Loop(elem, range) => if range.max != Maxable::Max(0) {
+                elem.leftmost_rule_ids(nullable_rules, ids);
+            },
+            Element::CatchSkip(elem, _, to) => {
+                elem.leftmost_rule_ids(nullable_rules, ids);
+                to.leftmost_rule_ids(nullable_rules, ids);
+            },
+            Element::PositiveLookahead(elem) | Element::NegativeLookahead(elem) | Element::Error(elem, _) | Element::Catch(elem, _) |
+                Element::TreeReduction(elem, _) | Element::Predicate(elem, _) | Element::Group(elem, _) | Element::Expansion(elem) | Element::ExpansionOnce(elem) |
+                Element::Join(elem) | Element::Hidden(elem) | Element::Lexical(elem) | Element::Capture(elem, _) => elem.leftmost_rule_ids(nullable_rules, ids),
+            Element::Precedence(atom, levels) => {
+                atom.leftmost_rule_ids(nullable_rules, ids);
+
+                // Infix and postfix operators follow the atom, so they are leftmost only when the atom is nullable.
+                for each_level in levels {
+                    if each_level.associativity == Associativity::Prefix || atom.is_nullable(nullable_rules) {
+                        for each_operator in &each_level.operators {
+                            each_operator.leftmost_rule_ids(nullable_rules, ids);
+                        }
+                    }
+                }
+            },
+            Element::Cut | Element::Throw(_) | Element::Indentation(_) => (),
+        }
+    }
+
+    fn children(&self) -> Vec<&Element> {
+        match self {
+            Element::Expression(_) | Element::Cut | Element::Throw(_) | Element::Indentation(_) => Vec::new(),
+            Element::Choice(elems) | Element::Sequence(elems) => elems.iter().collect(),
+            Element::CatchSkip(elem, _, to) => vec![elem, to],
+            Element::Loop(elem, _) | Element::PositiveLookahead(elem) | Element::NegativeLookahead(elem) | Element::Error(elem, _) |
+                Element::Catch(elem, _) | Element::TreeReduction(elem, _) | Element::Predicate(elem, _) | Element::Group(elem, _) | Element::Expansion(elem) |
+                Element::ExpansionOnce(elem) | Element::Join(elem) | Element::Hidden(elem) | Element::Lexical(elem) | Element::Capture(elem, _) => vec![elem],
+            Element::Precedence(atom, levels) => {
+                let mut elems = vec![atom.as_ref()];
+                elems.extend(levels.iter().flat_map(|level| level.operators.iter()));
+                elems
+            },
+        }
+    }
+
+    pub(crate) fn rule_ids(&self, ids: &mut Vec<RuleId>) {
+        if let Element::Expression(Expression::Rule(id)) = self {
+            if !ids.contains(id) {
+                ids.push(id.clone());
+            }
+        }
+
+        for each_child in self.children() {
+            each_child.rule_ids(ids);
+        }
+    }
+
+    // Collects unlimited loops which never end when the body matches without consuming input.
+    pub(crate) fn nullable_loops<'a>(&'a self, nullable_rules: &HashSet<RuleId>, loops: &mut Vec<&'a Element>) {
+        if let Element::Loop(elem, range) = self {
+            if range.max == Maxable::NoLimit && elem.is_nullable(nullable_rules) {
+                loops.push(self);
+            }
+        }
+
+        for each_child in self.children() {
+            each_child.nullable_loops(nullable_rules, loops);
+        }
+    }
 }
 
 impl Display for Element {
@@ -131,38 +284,162 @@ impl Display for Element {
             Element::Expression(expr) => expr.to_string(),
             Element::Choice(elems) => format!("({})", elems.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(" / ")),
             Element::Sequence(elems) => format!("({})", elems.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(" ")),
-            Element::Loop(elem, range) => format!("{}{}", elem, range),
+            Element::Loop(elem, range) => format!("{}{}", elem.to_operand_string(), range),
             Element::PositiveLookahead(elem) => format!("&{}", elem),
             Element::NegativeLookahead(elem) => format!("!{}", elem),
-            Element::Error(elem, message) => format!("{}.err({})", elem, message),
-            Element::Catch(elem, message) => format!("{}.catch({})", elem, message),
-            Element::CatchSkip(elem, message, to) => format!("{}.catch_to({}, {})", elem, to, message),
-            Element::TreeReduction(elem, _) => format!("{}.reduce", elem),
-            Element::Group(elem, name) => format!("{}#{}", elem, name),
-            Element::Expansion(elem) | Element::ExpansionOnce(elem) => format!("{}###", elem),
-            Element::Join(elem) => format!("{}.join", elem),
-            Element::Hidden(elem) => format!("{}##", elem),
+            Element::Error(elem, message) => format!("{}.err(\"{}\")", elem.to_operand_string(), escape(message)),
+            Element::Catch(elem, message) => format!("{}.catch(\"{}\")", elem.to_operand_string(), escape(message)),
+            Element::CatchSkip(elem, message, to) => format!("{}.catch_to({}, \"{}\")", elem.to_operand_string(), to, escape(message)),
+            // Closures can't be printed, so `.reduce` and `.check` aren't loadable by the grammar loader.
+            Element::TreeReduction(elem, _) => format!("{}.reduce", elem.to_operand_string()),
+            Element::Predicate(elem, _) => format!("{}.check", elem.to_operand_string()),
+            Element::Group(elem, name) => format!("{}#{}", elem.to_operand_string(), name),
+            Element::Expansion(elem) => format!("{}###", elem.to_operand_string()),
+            Element::ExpansionOnce(elem) => format!("{}.expand_once", elem.to_operand_string()),
+            Element::Join(elem) => format!("{}.join", elem.to_operand_string()),
+            Element::Hidden(elem) => format!("{}##", elem.to_operand_string()),
+            Element::Lexical(elem) => format!("{}.lexical", elem.to_operand_string()),
+            Element::Precedence(atom, levels) => format!("{}.precedence({})", atom.to_operand_string(), levels.iter().map(|level| level.to_string()).collect::<Vec<String>>().join(", ")),
+            Element::Cut => "~".to_string(),
+            Element::Throw(label) => format!("throw(\"{}\")", escape(label)),
+            Element::Capture(elem, name) => format!("{}.capture(\"{}\")", elem.to_operand_string(), escape(name)),
+            Element::Indentation(indentation) => indentation.to_string(),
         };
 
         write!(f, "{}", s)
     }
 }
 
+impl Element {
+    // Lookahead binds looser than suffixes, so it's enclosed when suffixed.
+    fn to_operand_string(&self) -> String {
+        match self {
+            Element::PositiveLookahead(_) | Element::NegativeLookahead(_) => format!("({})", self),
+            _ => self.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Associativity {
+    Left,
+    Right,
+    Prefix,
+    Postfix,
+}
+
+impl Display for Associativity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Associativity::Left => "left",
+            Associativity::Right => "right",
+            Associativity::Prefix => "prefix",
+            Associativity::Postfix => "postfix",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone)]
+pub struct PrecedenceLevel {
+    pub associativity: Associativity,
+    // Operators are tried in order.
+    pub operators: Vec<Element>,
+}
+
+impl PrecedenceLevel {
+    pub fn new(associativity: Associativity, operators: Vec<Element>) -> PrecedenceLevel {
+        PrecedenceLevel {
+            associativity,
+            operators,
+        }
+    }
+
+    pub fn left(operators: Vec<Element>) -> PrecedenceLevel {
+        PrecedenceLevel::new(Associativity::Left, operators)
+    }
+
+    pub fn right(operators: Vec<Element>) -> PrecedenceLevel {
+        PrecedenceLevel::new(Associativity::Right, operators)
+    }
+
+    pub fn prefix(operators: Vec<Element>) -> PrecedenceLevel {
+        PrecedenceLevel::new(Associativity::Prefix, operators)
+    }
+
+    pub fn postfix(operators: Vec<Element>) -> PrecedenceLevel {
+        PrecedenceLevel::new(Associativity::Postfix, operators)
+    }
+}
+
+impl Display for PrecedenceLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.associativity, self.operators.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(" / "))
+    }
+}
+
+// Compares the column of the next non-blank input with the top of the indentation stack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indentation {
+    // Pushes a deeper column.
+    Indent,
+    // Pops the top for a shallower column.
+    Dedent,
+    Same,
+}
+
+impl Display for Indentation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Indentation::Indent => write!(f, "indent()"),
+            Indentation::Dedent => write!(f, "dedent()"),
+            Indentation::Same => write!(f, "same_indent()"),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum Expression {
     Rule(RuleId),
     String(String),
     CharacterClass(Regex),
     Wildcard,
+    // Matches a token of the kind in token streams.
+    Token(String),
+    // Matches input captured under the name in the current rule.
+    Backreference(String),
+}
+
+impl Debug for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expression::Rule(a), Expression::Rule(b)) => a == b,
+            (Expression::String(a), Expression::String(b)) => a == b,
+            (Expression::CharacterClass(a), Expression::CharacterClass(b)) => a.as_str() == b.as_str(),
+            (Expression::Wildcard, Expression::Wildcard) => true,
+            (Expression::Token(a), Expression::Token(b)) => a == b,
+            (Expression::Backreference(a), Expression::Backreference(b)) => a == b,
+            _ => false,
+        }
+    }
 }
 
 impl Display for Expression {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let s = match self {
             Expression::Rule(id) => id.to_string(),
-            Expression::String(v) => format!("\"{}\"", v),
+            Expression::String(v) => format!("\"{}\"", escape(v)),
             Expression::CharacterClass(v) => format!("{}", v),
             Expression::Wildcard => "_".to_string(),
+            Expression::Token(kind) => format!("<{}>", kind),
+            Expression::Backreference(name) => format!("backref(\"{}\")", escape(name)),
         };
 
         write!(f, "{}", s)
@@ -183,25 +460,14 @@ pub struct LoopRange {
 
 impl Display for LoopRange {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self.min {
-            0 => match &self.max {
-                Maxable::Max(v) if *v == 1 => return write!(f, "_"),
-                Maxable::NoLimit => return write!(f, "*"),
-                _ => (),
-            },
-            1 => match &self.max {
-                Maxable::NoLimit => return write!(f, "*"),
-                _ => (),
-            },
-            _ => (),
-        };
-
-        let max = match &self.max {
-            Maxable::Max(v) => v.to_string(),
-            Maxable::NoLimit => String::new(),
-        };
-
-        write!(f, "{{{},{}}}", self.min, max)
+        match (self.min, &self.max) {
+            (0, Maxable::Max(1)) => write!(f, "?"),
+            (0, Maxable::NoLimit) => write!(f, "*"),
+            (1, Maxable::NoLimit) => write!(f, "+"),
+            (min, Maxable::Max(max)) if min == *max => write!(f, "{{{}}}", min),
+            (min, Maxable::Max(max)) => write!(f, "{{{},{}}}", min, max),
+            (min, Maxable::NoLimit) => write!(f, "{{{},}}", min),
+        }
     }
 }
 
@@ -236,27 +502,81 @@ impl LoopRange {
     }
 }
 
+pub(crate) fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+
+    for ch in s.chars() {
+        match ch {
+            '\\' => escaped += "\\\\",
+            '"' => escaped += "\\\"",
+            '\n' => escaped += "\\n",
+            '\r' => escaped += "\\r",
+            '\t' => escaped += "\\t",
+            _ => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
 pub fn str(s: &str) -> Element {
-    if s.len() == 0 {
-        panic!("Empty string is not allowed.");
+    try_str(s).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_str(s: &str) -> GrammarResult<Element> {
+    if s.is_empty() {
+        return Err(GrammarError::EmptyString { position: None });
     }
 
-    Element::Expression(Expression::String(s.to_string()))
+    Ok(Element::Expression(Expression::String(s.to_string())))
 }
 
 pub fn chars(s: &str) -> Element {
-    // todo: check pattern
+    try_chars(s).unwrap_or_else(|e| panic!("{}", e))
+}
+
+pub fn try_chars(s: &str) -> GrammarResult<Element> {
     let patt = format!("[{}]", s.replace("[", "\\[").replace("]", "\\]"));
 
-    let regex = if let Ok(v) = Regex::new(&patt) {
-        v
-    } else {
-        panic!("invalid regex pattern");
-    };
+    match Regex::new(&patt) {
+        Ok(regex) => Ok(Element::Expression(Expression::CharacterClass(regex))),
+        Err(_) => Err(GrammarError::InvalidCharacterClass { pattern: s.to_string(), position: None }),
+    }
+}
 
-    Element::Expression(Expression::CharacterClass(regex))
+// Levels are ordered from the loosest binding to the tightest.
+pub fn precedence(atom: Element, levels: Vec<PrecedenceLevel>) -> Element {
+    Element::Precedence(Box::new(atom), levels)
 }
 
 pub fn wildcard() -> Element {
     Element::Expression(Expression::Wildcard)
 }
+
+pub fn cut() -> Element {
+    Element::Cut
+}
+
+pub fn throw(label: &str) -> Element {
+    Element::Throw(label.to_string())
+}
+
+pub fn backref(name: &str) -> Element {
+    Element::Expression(Expression::Backreference(name.to_string()))
+}
+
+pub fn indent() -> Element {
+    Element::Indentation(Indentation::Indent)
+}
+
+pub fn dedent() -> Element {
+    Element::Indentation(Indentation::Dedent)
+}
+
+pub fn same_indent() -> Element {
+    Element::Indentation(Indentation::Same)
+}
+
+pub fn token(kind: &str) -> Element {
+    Element::Expression(Expression::Token(kind.to_string()))
+}
@@ -0,0 +1,410 @@
+use {
+    // Use volt to resolve items in derive macro.
+    crate as volt,
+    std::collections::HashSet,
+    std::fmt::{self, Display, Formatter},
+    regex::Regex,
+    volt_derive::VoltModuleDefinition,
+    crate::{
+        *,
+        parser::ParserError,
+        tree::*,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GrammarError {
+    Syntax(ParserError),
+    UnknownRule { id: RuleId, position: InputPosition },
+    DuplicateRule { id: RuleId },
+    // Positions are None when the element isn't loaded from grammar source.
+    EmptyString { position: Option<InputPosition> },
+    InvalidCharacterClass { pattern: String, position: Option<InputPosition> },
+    InvalidLoopRange { position: InputPosition },
+}
+
+impl Display for GrammarError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GrammarError::Syntax(e) => write!(f, "invalid grammar syntax: {}", e),
+            GrammarError::UnknownRule { id, position } => write!(f, "unknown rule `{}` at {}", id, position),
+            GrammarError::DuplicateRule { id } => write!(f, "rule ID `{}` is already declared", id),
+            GrammarError::EmptyString { position: Some(position) } => write!(f, "empty string is not allowed at {}", position),
+            GrammarError::EmptyString { position: None } => write!(f, "empty string is not allowed"),
+            GrammarError::InvalidCharacterClass { pattern, position: Some(position) } => write!(f, "invalid character class `{}` at {}", pattern, position),
+            GrammarError::InvalidCharacterClass { pattern, position: None } => write!(f, "invalid character class `{}`", pattern),
+            GrammarError::InvalidLoopRange { position } => write!(f, "invalid loop range at {}", position),
+        }
+    }
+}
+
+impl std::error::Error for GrammarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GrammarError::Syntax(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+pub type GrammarResult<T> = Result<T, GrammarError>;
+
+// Rules loaded from grammar source with rule references and their positions.
+pub(crate) struct GrammarSource {
+    pub(crate) rules: RuleVec,
+    pub(crate) references: Vec<(RuleId, InputPosition)>,
+}
+
+impl GrammarSource {
+    pub(crate) fn load(module_name: &str, source: &str) -> GrammarResult<GrammarSource> {
+        let volt = &mut Volt::new();
+        volt.add_module(GrammarModule::new());
+
+        let tree = volt.parse(source, &RuleId("GrammarModule::grammar".to_string())).map_err(GrammarError::Syntax)?;
+
+        let mut builder = GrammarBuilder {
+            module_name,
+            references: Vec::new(),
+        };
+
+        let mut rules = Vec::new();
+        let mut ids = HashSet::new();
+
+        for each_definition in tree.root.children.filter_nodes() {
+            let rule = builder.definition(each_definition)?;
+
+            if !ids.insert(rule.id.clone()) {
+                return Err(GrammarError::DuplicateRule { id: rule.id });
+            }
+
+            rules.push(rule);
+        }
+
+        Ok(GrammarSource {
+            rules: RuleVec(rules),
+            references: builder.references,
+        })
+    }
+}
+
+pub fn parse_grammar(module_name: &str, source: &str) -> GrammarResult<RuleVec> {
+    GrammarSource::load(module_name, source).map(|source| source.rules)
+}
+
+struct GrammarBuilder<'a> {
+    module_name: &'a str,
+    references: Vec<(RuleId, InputPosition)>,
+}
+
+impl<'a> GrammarBuilder<'a> {
+    fn rule_id(&self, name: &str) -> RuleId {
+        if name.contains("::") {
+            RuleId(name.to_string())
+        } else {
+            RuleId(format!("{}::{}", self.module_name, name))
+        }
+    }
+
+    fn definition(&mut self, node: &SyntaxNode) -> GrammarResult<Rule> {
+        let id = self.rule_id(&node.children.get_leaf(0).value);
+        let element = self.choice(node.children.get_node(1))?;
+        Ok(Rule::new(id, element))
+    }
+
+    fn choice(&mut self, node: &SyntaxNode) -> GrammarResult<Element> {
+        let mut elems = Vec::new();
+
+        for each_sequence in node.children.filter_nodes() {
+            elems.push(self.sequence(each_sequence)?);
+        }
+
+        Ok(if elems.len() == 1 { elems.remove(0) } else { Element::Choice(elems) })
+    }
+
+    fn sequence(&mut self, node: &SyntaxNode) -> GrammarResult<Element> {
+        let mut elems = Vec::new();
+
+        for each_prefixed in node.children.filter_nodes() {
+            elems.push(self.prefixed(each_prefixed)?);
+        }
+
+        Ok(if elems.len() == 1 { elems.remove(0) } else { Element::Sequence(elems) })
+    }
+
+    fn prefixed(&mut self, node: &SyntaxNode) -> GrammarResult<Element> {
+        let elem = self.suffixed(node.children.find_node("GrammarModule::suffixed"))?;
+
+        let prefixed = match node.children.get_leaf_or_none(0).map(|leaf| leaf.value.as_str()) {
+            Some("&") => elem.poslook(),
+            Some("!") => elem.neglook(),
+            _ => elem,
+        };
+
+        Ok(prefixed)
+    }
+
+    fn suffixed(&mut self, node: &SyntaxNode) -> GrammarResult<Element> {
+        let nodes = node.children.filter_nodes();
+        let mut elem = self.primary(nodes[0])?;
+
+        for each_suffix in &nodes[1..] {
+            elem = self.suffix(elem, each_suffix)?;
+        }
+
+        Ok(elem)
+    }
+
+    fn primary(&mut self, node: &SyntaxNode) -> GrammarResult<Element> {
+        let primary = node.children.get_node(0);
+
+        let elem = match primary.name.as_str() {
+            "GrammarModule::choice" => self.choice(primary)?,
+            "GrammarModule::wildcard" => wildcard(),
+            "cut" => cut(),
+            "throw" => throw(&message(primary.children.get_node(0))),
+            "backref" => backref(&message(primary.children.get_node(0))),
+            "indentation" => match primary.children.get_leaf(0).value.as_str() {
+                "indent()" => indent(),
+                "dedent()" => dedent(),
+                _ => same_indent(),
+            },
+            "GrammarModule::rule_reference" => {
+                let leaf = primary.children.get_leaf(0);
+                let id = self.rule_id(&leaf.value);
+                self.references.push((id.clone(), leaf.span.start.clone()));
+                Element::Expression(Expression::Rule(id))
+            },
+            "GrammarModule::string" => {
+                let leaf = primary.children.get_leaf(0);
+                let s = unescape(&leaf.value);
+
+                if s.is_empty() {
+                    return Err(GrammarError::EmptyString { position: Some(leaf.span.start.clone()) });
+                }
+
+                str(&s)
+            },
+            "GrammarModule::token" => token(&primary.children.get_leaf(0).value),
+            "GrammarModule::character_class" => {
+                let leaf = primary.children.get_leaf(0);
+
+                match Regex::new(&leaf.value) {
+                    Ok(regex) => Element::Expression(Expression::CharacterClass(regex)),
+                    Err(_) => return Err(GrammarError::InvalidCharacterClass { pattern: leaf.value.clone(), position: Some(leaf.span.start.clone()) }),
+                }
+            },
+            _ => unreachable!("unknown primary `{}`", primary.name),
+        };
+
+        Ok(elem)
+    }
+
+    fn suffix(&mut self, elem: Element, node: &SyntaxNode) -> GrammarResult<Element> {
+        let suffix = match node.children.get_child(0) {
+            SyntaxChild::Leaf(leaf) => match leaf.value.as_str() {
+                "?" => elem.optional(),
+                "*" => elem.min(0),
+                "+" => elem.min(1),
+                "###" => elem.expand(),
+                "##" => elem.hide(),
+                ".join" => elem.join(),
+                ".expand_once" => elem.expand_once(),
+                ".lexical" => elem.lexical(),
+                _ => unreachable!("unknown suffix `{}`", leaf.value),
+            },
+            SyntaxChild::Node(suffix) => match suffix.name.as_str() {
+                "range" => elem.range(self.loop_range(suffix)?),
+                "group" => elem.group(&suffix.children.get_leaf(0).value),
+                "err" => elem.err(&message(suffix.children.get_node(0))),
+                "catch" => elem.catch(&message(suffix.children.get_node(0))),
+                "capture" => elem.capture(&message(suffix.children.get_node(0))),
+                "catch_to" => {
+                    let to = self.choice(suffix.children.get_node(0))?;
+                    elem.catch_to(&message(suffix.children.get_node(1)), to)
+                },
+                "precedence" => {
+                    let mut levels = Vec::new();
+
+                    for each_level in suffix.children.filter_nodes() {
+                        levels.push(self.precedence_level(each_level)?);
+                    }
+
+                    precedence(elem, levels)
+                },
+                _ => unreachable!("unknown suffix `{}`", suffix.name),
+            },
+            SyntaxChild::Error(_) => unreachable!("unexpected syntax error"),
+        };
+
+        Ok(suffix)
+    }
+
+    // Each sequence of the level is an operator so that parenthesized choices stay single operators.
+    fn precedence_level(&mut self, node: &SyntaxNode) -> GrammarResult<PrecedenceLevel> {
+        let associativity = match node.children.get_leaf(0).value.as_str() {
+            "left" => Associativity::Left,
+            "right" => Associativity::Right,
+            "prefix" => Associativity::Prefix,
+            _ => Associativity::Postfix,
+        };
+
+        let mut operators = Vec::new();
+
+        for each_sequence in node.children.get_node(1).children.filter_nodes() {
+            operators.push(self.sequence(each_sequence)?);
+        }
+
+        Ok(PrecedenceLevel::new(associativity, operators))
+    }
+
+    fn loop_range(&mut self, node: &SyntaxNode) -> GrammarResult<LoopRange> {
+        let min_leaf = node.children.get_leaf(0);
+        let parse_number = |leaf: &SyntaxLeaf| leaf.value.parse::<usize>().map_err(|_| GrammarError::InvalidLoopRange { position: leaf.span.start.clone() });
+        let min = parse_number(min_leaf)?;
+
+        let range = match (node.children.get_leaf_or_none(1), node.children.get_leaf_or_none(2)) {
+            (None, _) => LoopRange::times(min),
+            (Some(_), None) => LoopRange::min(min),
+            (Some(_), Some(max_leaf)) => LoopRange::min_max(min, parse_number(max_leaf)?),
+        };
+
+        match range.max {
+            Maxable::Max(max) if max < min || max == 0 => Err(GrammarError::InvalidLoopRange { position: min_leaf.span.start.clone() }),
+            _ => Ok(range),
+        }
+    }
+}
+
+fn message(string_node: &SyntaxNode) -> String {
+    unescape(&string_node.children.get_leaf(0).value)
+}
+
+// Strips enclosing quotes and resolves escape sequences.
+fn unescape(quoted: &str) -> String {
+    let mut s = String::new();
+    let mut chars = quoted[1..quoted.len() - 1].chars();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('r') => s.push('\r'),
+                Some('t') => s.push('\t'),
+                Some(escaped) => s.push(escaped),
+                None => (),
+            }
+        } else {
+            s.push(ch);
+        }
+    }
+
+    s
+}
+
+#[derive(VoltModuleDefinition)]
+struct GrammarModule {
+    grammar: Element,
+    definition: Element,
+    choice: Element,
+    sequence: Element,
+    prefixed: Element,
+    suffixed: Element,
+    primary: Element,
+    suffix: Element,
+    wildcard: Element,
+    rule_reference: Element,
+    rule_name: Element,
+    identifier: Element,
+    string: Element,
+    character_class: Element,
+    token: Element,
+    precedence_level: Element,
+    number: Element,
+    spacing: Element,
+}
+
+impl VoltModule for GrammarModule {
+    fn new() -> GrammarModule {
+        define_rules!{
+            grammar := seq![GrammarModule::spacing().hide(), GrammarModule::definition().min(0)];
+            definition := seq![
+                GrammarModule::rule_name().expand(),
+                GrammarModule::spacing().hide(),
+                str(":=").hide(),
+                GrammarModule::spacing().hide(),
+                GrammarModule::choice(),
+                str(";").hide(),
+                GrammarModule::spacing().hide(),
+            ];
+            choice := seq![GrammarModule::sequence(), seq![str("/").hide(), GrammarModule::spacing().hide(), GrammarModule::sequence()].min(0)];
+            sequence := GrammarModule::prefixed().min(1);
+            prefixed := seq![choice![str("&"), str("!")].optional(), GrammarModule::suffixed()];
+            suffixed := seq![GrammarModule::primary(), GrammarModule::suffix().min(0), GrammarModule::spacing().hide()];
+            primary := choice![
+                seq![str("(").hide(), GrammarModule::spacing().hide(), GrammarModule::choice(), str(")").hide()],
+                seq![str("throw(").hide(), GrammarModule::string(), str(")").hide()].group("throw"),
+                seq![str("backref(").hide(), GrammarModule::string(), str(")").hide()].group("backref"),
+                choice![str("indent()"), str("dedent()"), str("same_indent()")].group("indentation"),
+                GrammarModule::wildcard(),
+                GrammarModule::rule_reference(),
+                GrammarModule::string(),
+                GrammarModule::character_class(),
+                GrammarModule::token(),
+                str("~").group("cut"),
+            ];
+            suffix := choice![
+                str("?"),
+                str("*"),
+                str("+"),
+                seq![
+                    str("{").hide(),
+                    GrammarModule::number().expand(),
+                    seq![str(","), GrammarModule::number().expand().optional()].optional(),
+                    str("}").hide(),
+                ].group("range"),
+                str("###"),
+                str("##"),
+                seq![str("#").hide(), GrammarModule::rule_name().expand()].group("group"),
+                seq![str(".err(").hide(), GrammarModule::string(), str(")").hide()].group("err"),
+                seq![str(".catch(").hide(), GrammarModule::string(), str(")").hide()].group("catch"),
+                seq![str(".capture(").hide(), GrammarModule::string(), str(")").hide()].group("capture"),
+                seq![
+                    str(".catch_to(").hide(),
+                    GrammarModule::spacing().hide(),
+                    GrammarModule::choice(),
+                    str(",").hide(),
+                    GrammarModule::spacing().hide(),
+                    GrammarModule::string(),
+                    str(")").hide(),
+                ].group("catch_to"),
+                seq![
+                    str(".precedence(").hide(),
+                    GrammarModule::spacing().hide(),
+                    GrammarModule::precedence_level(),
+                    seq![str(",").hide(), GrammarModule::spacing().hide(), GrammarModule::precedence_level()].min(0),
+                    str(")").hide(),
+                ].group("precedence"),
+                str(".join"),
+                str(".expand_once"),
+                str(".lexical"),
+            ];
+            wildcard := seq![str("_"), chars("a-zA-Z0-9_").neglook()].hide();
+            rule_reference := GrammarModule::rule_name().expand();
+            rule_name := seq![GrammarModule::identifier(), seq![str("::"), GrammarModule::identifier()].optional()].join();
+            identifier := seq![chars("a-zA-Z_"), chars("a-zA-Z0-9_").min(0)];
+            string := seq![str("\""), choice![seq![str("\\"), wildcard()], seq![str("\"").neglook(), wildcard()]].min(0), str("\"")].join();
+            character_class := seq![str("["), choice![seq![str("\\"), wildcard()], seq![str("]").neglook(), wildcard()]].min(0), str("]")].join();
+            token := seq![str("<").hide(), GrammarModule::identifier().expand(), str(">").hide()].join();
+            precedence_level := seq![
+                choice![str("left"), str("right"), str("prefix"), str("postfix")],
+                str("(").hide(),
+                GrammarModule::spacing().hide(),
+                GrammarModule::choice(),
+                str(")").hide(),
+                GrammarModule::spacing().hide(),
+            ];
+            number := chars(r"\d").min(1).join();
+            spacing := choice![chars(" \t\r\n"), seq![str("//"), seq![str("\n").neglook(), wildcard()].min(0)]].min(0);
+        }
+    }
+}
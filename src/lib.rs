@@ -1,15 +1,26 @@
+mod analysis;
+pub mod diagnostic;
 pub mod element;
+pub mod grammar;
+pub mod observer;
 pub mod parser;
 pub mod rule;
 pub mod tree;
+pub mod validation;
+pub mod visitor;
 #[cfg(test)]
 mod tests;
 
 use {
     std::collections::HashMap,
+    analysis::LeftRecursion,
     element::*,
+    grammar::*,
+    observer::ParseObserver,
     parser::*,
     rule::*,
+    tree::SyntaxTree,
+    validation::ValidationIssue,
 };
 
 #[macro_export]
@@ -42,38 +53,137 @@ macro_rules! seq {
 pub struct Volt {
     // todo: Optimize process speed of HashMap.
     rule_map: HashMap<RuleId, Element>,
+    left_recursion: LeftRecursion,
     max_recursion: usize,
+    memoization: bool,
+    // Trivia rule IDs by module name.
+    trivia: HashMap<String, RuleId>,
+    trivia_preservation: bool,
+    lossless: bool,
+    // Recovery elements by failure label.
+    recovery: HashMap<String, Element>,
+}
+
+impl Default for Volt {
+    fn default() -> Volt {
+        Volt::new()
+    }
 }
 
 impl Volt {
     pub fn new() -> Volt {
         Volt {
             rule_map: HashMap::new(),
+            left_recursion: LeftRecursion::default(),
             max_recursion: 1024,
+            memoization: false,
+            trivia: HashMap::new(),
+            trivia_preservation: false,
+            lossless: false,
+            recovery: HashMap::new(),
         }
     }
 
     pub fn add_module<T: VoltModuleAssist>(&mut self, module: T) {
+        if let Err(e) = self.try_add_module(module) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_add_module<T: VoltModuleAssist>(&mut self, module: T) -> GrammarResult<()> {
         let rules: Vec<Rule> = module.into_rule_vec().into();
+        self.check_duplicate_rules(&rules)?;
+        self.insert_rules(rules);
+        Ok(())
+    }
 
-        for each_rule in rules {
-            let id = each_rule.id.clone();
+    pub fn add_grammar_str(&mut self, module_name: &str, source: &str) -> GrammarResult<()> {
+        let GrammarSource { rules, references } = GrammarSource::load(module_name, source)?;
+        let rules: Vec<Rule> = rules.into();
+        self.check_duplicate_rules(&rules)?;
+
+        for (id, position) in references {
+            if !self.rule_map.contains_key(&id) && !rules.iter().any(|r| r.id == id) {
+                return Err(GrammarError::UnknownRule { id, position });
+            }
+        }
+
+        self.insert_rules(rules);
+        Ok(())
+    }
 
-            if self.rule_map.contains_key(&id) {
-                panic!("Rule ID `{}` is already declared.", id);
+    // Checks all rules before insertion so that no rule is registered on failure.
+    fn check_duplicate_rules(&self, rules: &[Rule]) -> GrammarResult<()> {
+        for (i, each_rule) in rules.iter().enumerate() {
+            if self.rule_map.contains_key(&each_rule.id) || rules[..i].iter().any(|r| r.id == each_rule.id) {
+                return Err(GrammarError::DuplicateRule { id: each_rule.id.clone() });
             }
+        }
+
+        Ok(())
+    }
 
-            self.rule_map.insert(id, each_rule.element);
+    fn insert_rules(&mut self, rules: Vec<Rule>) {
+        for each_rule in rules {
+            self.rule_map.insert(each_rule.id, each_rule.element);
         }
+
+        self.left_recursion = LeftRecursion::detect(&self.rule_map);
     }
 
     pub fn set_max_recursion(&mut self, max_recursion: usize) {
         self.max_recursion = max_recursion;
     }
 
+    pub fn set_memoization(&mut self, memoization: bool) {
+        self.memoization = memoization;
+    }
+
+    // Rules of the module skip the trivia rule between elements unless they are lexical.
+    pub fn set_trivia(&mut self, module_name: &str, trivia_rule_id: RuleId) {
+        self.trivia.insert(module_name.to_string(), trivia_rule_id);
+    }
+
+    // Keeps skipped trivia as hidden leaves.
+    pub fn set_trivia_preservation(&mut self, trivia_preservation: bool) {
+        self.trivia_preservation = trivia_preservation;
+    }
+
+    // Keeps hidden, skipped and trivia input as hidden leaves so that `SyntaxTree::to_source()` reproduces the input.
+    pub fn set_lossless(&mut self, lossless: bool) {
+        self.lossless = lossless;
+    }
+
+    // Labels thrown by `throw()` are recovered by the element, which generates an error with the label as its message.
+    pub fn set_recovery(&mut self, label: &str, recovery: Element) {
+        self.recovery.insert(label.to_string(), recovery);
+    }
+
+    pub fn validate(&self, entry_rule_id: &RuleId) -> Vec<ValidationIssue> {
+        let mut implicit_rule_ids: Vec<RuleId> = self.trivia.values().cloned().collect();
+
+        for each_recovery in self.recovery.values() {
+            each_recovery.rule_ids(&mut implicit_rule_ids);
+        }
+
+        validation::validate(&self.rule_map, entry_rule_id, &implicit_rule_ids)
+    }
+
     pub fn parse(&self, input: &str, entry_rule_id: &RuleId) -> ParserResult {
         Parser::parse(&self, input, entry_rule_id)
     }
+
+    pub fn parse_tokens(&self, tokens: &[Token], entry_rule_id: &RuleId) -> ParserResult {
+        Parser::parse(self, tokens, entry_rule_id)
+    }
+
+    pub fn reparse(&self, tree: &SyntaxTree, input: &str, edit: &TextEdit, entry_rule_id: &RuleId) -> ParserResult {
+        Parser::reparse(self, tree, input, edit, entry_rule_id)
+    }
+
+    pub fn parse_with_observer(&self, input: &str, entry_rule_id: &RuleId, observer: &mut dyn ParseObserver) -> ParserResult {
+        Parser::parse_with_observer(self, input, entry_rule_id, observer)
+    }
 }
 
 pub trait VoltModule: VoltModuleAssist {
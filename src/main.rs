@@ -0,0 +1,297 @@
+use {
+    std::{
+        env,
+        fs,
+        io::{self, IsTerminal, Read, Write},
+        path::Path,
+        process::ExitCode,
+    },
+    volt::{
+        *,
+        diagnostic::Diagnostic,
+        rule::RuleId,
+        tree::*,
+    },
+};
+
+// JSON output is available only with `serde` feature.
+#[cfg(feature = "serde")]
+const USAGE: &str = "usage: volt <grammar> <entry> [input] [--module <name>] [--format tree|json|sexp]";
+#[cfg(not(feature = "serde"))]
+const USAGE: &str = "usage: volt <grammar> <entry> [input] [--module <name>] [--format tree|sexp]";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Tree,
+    #[cfg(feature = "serde")]
+    Json,
+    Sexp,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    grammar_path: String,
+    entry: String,
+    // Reads stdin if None.
+    input_path: Option<String>,
+    // Defaults to the file stem of the grammar.
+    module_name: Option<String>,
+    format: Format,
+}
+
+fn main() -> ExitCode {
+    let colored = io::stderr().is_terminal();
+    execute(env::args().skip(1), &mut io::stdout(), &mut io::stderr(), colored)
+}
+
+// Exits with 1 on parsing failures and 2 on invalid arguments or unreadable files. Write errors such as a closed pipe are ignored.
+fn execute(args: impl Iterator<Item = String>, stdout: &mut impl Write, stderr: &mut impl Write, colored: bool) -> ExitCode {
+    let args = match parse_args(args) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            let _ = writeln!(stdout, "{}", USAGE);
+            return ExitCode::SUCCESS;
+        },
+        Err(message) => {
+            let _ = writeln!(stderr, "error: {}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        },
+    };
+
+    match run(&args, stdout, stderr, colored) {
+        Ok(code) => code,
+        Err(message) => {
+            let _ = writeln!(stderr, "error: {}", message);
+            ExitCode::from(2)
+        },
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut positional = Vec::new();
+    let mut module_name = None;
+    let mut format = Format::Tree;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--module" => module_name = Some(args.next().ok_or("missing value of `--module`")?),
+            "--format" => format = match args.next().as_deref() {
+                Some("tree") => Format::Tree,
+                #[cfg(feature = "serde")]
+                Some("json") => Format::Json,
+                #[cfg(not(feature = "serde"))]
+                Some("json") => return Err("`json` format requires `serde` feature".to_string()),
+                Some("sexp") => Format::Sexp,
+                Some(v) => return Err(format!("unknown format `{}`", v)),
+                None => return Err("missing value of `--format`".to_string()),
+            },
+            _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+            _ => positional.push(arg),
+        }
+    }
+
+    if positional.len() < 2 || positional.len() > 3 {
+        return Err("expected grammar, entry rule and optional input".to_string());
+    }
+
+    let mut positional = positional.into_iter();
+
+    Ok(Some(Args {
+        grammar_path: positional.next().unwrap(),
+        entry: positional.next().unwrap(),
+        input_path: positional.next(),
+        module_name,
+        format,
+    }))
+}
+
+fn run(args: &Args, stdout: &mut impl Write, stderr: &mut impl Write, colored: bool) -> Result<ExitCode, String> {
+    let grammar = fs::read_to_string(&args.grammar_path).map_err(|e| format!("cannot read `{}`: {}", args.grammar_path, e))?;
+
+    let module_name = match &args.module_name {
+        Some(v) => v.clone(),
+        None => Path::new(&args.grammar_path).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default(),
+    };
+
+    let input = match &args.input_path {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("cannot read `{}`: {}", path, e))?,
+        None => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input).map_err(|e| format!("cannot read stdin: {}", e))?;
+            input
+        },
+    };
+
+    let volt = &mut Volt::new();
+    volt.add_grammar_str(&module_name, &grammar).map_err(|e| format!("cannot load `{}`: {}", args.grammar_path, e))?;
+
+    let entry_rule_id = if args.entry.contains("::") {
+        RuleId(args.entry.clone())
+    } else {
+        RuleId(format!("{}::{}", module_name, args.entry))
+    };
+
+    match volt.parse(&input, &entry_rule_id) {
+        Ok(tree) => {
+            for each_diagnostic in tree.diagnostics() {
+                let _ = write!(stderr, "{}", render(&each_diagnostic, &input, colored));
+            }
+
+            let _ = writeln!(stdout, "{}", format_tree(&tree, args.format)?);
+            Ok(ExitCode::SUCCESS)
+        },
+        Err(e) => {
+            let _ = write!(stderr, "{}", render(&Diagnostic::from(&e), &input, colored));
+            Ok(ExitCode::from(1))
+        },
+    }
+}
+
+fn render(diagnostic: &Diagnostic, input: &str, colored: bool) -> String {
+    if colored {
+        diagnostic.render_colored(input)
+    } else {
+        diagnostic.render(input)
+    }
+}
+
+fn format_tree(tree: &SyntaxTree, format: Format) -> Result<String, String> {
+    match format {
+        Format::Tree => Ok(tree.fmt(0).iter().map(|line| line.to_string()).collect::<Vec<String>>().join("\n")),
+        Format::Sexp => Ok(tree.to_sexp()),
+        #[cfg(feature = "serde")]
+        Format::Json => serde_json::to_string_pretty(tree).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        std::{env, fs, process::ExitCode},
+        speculate::speculate,
+        super::*,
+    };
+
+    // Writes a file into the temporary directory and returns its path.
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = env::temp_dir().join(format!("volt-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    speculate!{
+        before {
+            #[allow(unused)]
+            let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>().into_iter();
+
+            #[allow(unused)]
+            let execute_args = |args: Vec<String>| {
+                let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+                let code = execute(args.into_iter(), &mut stdout, &mut stderr, false);
+                (code, String::from_utf8(stdout).unwrap(), String::from_utf8(stderr).unwrap())
+            };
+        }
+
+        describe "argument parsing" {
+            it "parses positional arguments and options" {
+                assert_eq!(parse_args(args(&["g.peg", "main", "in.txt", "--module", "M", "--format", "sexp"])), Ok(Some(Args {
+                    grammar_path: "g.peg".to_string(),
+                    entry: "main".to_string(),
+                    input_path: Some("in.txt".to_string()),
+                    module_name: Some("M".to_string()),
+                    format: Format::Sexp,
+                })));
+            }
+
+            it "reads stdin and prints tree by default" {
+                assert_eq!(parse_args(args(&["g.peg", "main"])), Ok(Some(Args {
+                    grammar_path: "g.peg".to_string(),
+                    entry: "main".to_string(),
+                    input_path: None,
+                    module_name: None,
+                    format: Format::Tree,
+                })));
+            }
+
+            it "returns none for help" {
+                assert_eq!(parse_args(args(&["g.peg", "--help"])), Ok(None));
+                assert_eq!(parse_args(args(&["-h"])), Ok(None));
+            }
+
+            it "rejects invalid arguments" {
+                assert_eq!(parse_args(args(&["g.peg"])), Err("expected grammar, entry rule and optional input".to_string()));
+                assert_eq!(parse_args(args(&["g.peg", "main", "a", "b"])), Err("expected grammar, entry rule and optional input".to_string()));
+                assert_eq!(parse_args(args(&["g.peg", "main", "--verbose"])), Err("unknown option `--verbose`".to_string()));
+                assert_eq!(parse_args(args(&["g.peg", "main", "--format", "xml"])), Err("unknown format `xml`".to_string()));
+                assert_eq!(parse_args(args(&["g.peg", "main", "--format"])), Err("missing value of `--format`".to_string()));
+                assert_eq!(parse_args(args(&["g.peg", "main", "--module"])), Err("missing value of `--module`".to_string()));
+            }
+
+            it "accepts json format only with serde feature" {
+                let format = parse_args(args(&["g.peg", "main", "--format", "json"])).map(|args| args.unwrap().format);
+
+                #[cfg(feature = "serde")]
+                assert_eq!(format, Ok(Format::Json));
+                #[cfg(not(feature = "serde"))]
+                assert_eq!(format, Err("`json` format requires `serde` feature".to_string()));
+            }
+        }
+
+        describe "execution" {
+            it "prints tree and exits with 0" {
+                let grammar_path = temp_file("success.peg", r#"main := "a" "b";"#);
+                let input_path = temp_file("success.txt", "ab");
+
+                let (code, stdout, stderr) = execute_args(vec![grammar_path, "main".to_string(), input_path, "--module".to_string(), "M".to_string(), "--format".to_string(), "sexp".to_string()]);
+
+                assert_eq!(code, ExitCode::SUCCESS);
+                assert_eq!(stdout, "(M::main \"a\" \"b\")\n");
+                assert_eq!(stderr, "");
+            }
+
+            it "prints usage for help and exits with 0" {
+                let (code, stdout, _) = execute_args(vec!["--help".to_string()]);
+
+                assert_eq!(code, ExitCode::SUCCESS);
+                assert_eq!(stdout, format!("{}\n", USAGE));
+            }
+
+            it "prints diagnostic and exits with 1 on parsing failure" {
+                let grammar_path = temp_file("failure.peg", r#"main := "a" "b";"#);
+                let input_path = temp_file("failure.txt", "ac");
+
+                let (code, stdout, stderr) = execute_args(vec![grammar_path, "main".to_string(), input_path]);
+
+                assert_eq!(code, ExitCode::from(1));
+                assert_eq!(stdout, "");
+                assert!(stderr.starts_with("error: unexpected input, expected \"b\"\n"));
+            }
+
+            it "exits with 2 on invalid arguments" {
+                let (code, _, stderr) = execute_args(vec!["--verbose".to_string()]);
+
+                assert_eq!(code, ExitCode::from(2));
+                assert_eq!(stderr, format!("error: unknown option `--verbose`\n{}\n", USAGE));
+            }
+
+            it "exits with 2 on unreadable file" {
+                let missing_path = env::temp_dir().join("volt-missing.peg").to_string_lossy().to_string();
+                let (code, _, stderr) = execute_args(vec![missing_path.clone(), "main".to_string()]);
+
+                assert_eq!(code, ExitCode::from(2));
+                assert!(stderr.starts_with(&format!("error: cannot read `{}`", missing_path)));
+            }
+
+            it "exits with 2 on invalid grammar" {
+                let grammar_path = temp_file("invalid.peg", "main := ;");
+                let input_path = temp_file("invalid.txt", "");
+
+                let (code, _, stderr) = execute_args(vec![grammar_path.clone(), "main".to_string(), input_path]);
+
+                assert_eq!(code, ExitCode::from(2));
+                assert!(stderr.starts_with(&format!("error: cannot load `{}`", grammar_path)));
+            }
+        }
+    }
+}
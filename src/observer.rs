@@ -0,0 +1,68 @@
+use {
+    std::io::Write,
+    crate::{
+        element::Expression,
+        rule::RuleId,
+        tree::*,
+    },
+};
+
+// Receives parsing events. Rule expressions are reported by the rule hooks instead of `attempt_expression`.
+pub trait ParseObserver {
+    fn enter_rule(&mut self, _id: &RuleId, _position: &InputPosition) {}
+
+    fn succeed_rule(&mut self, _id: &RuleId, _span: &Span) {}
+
+    fn fail_rule(&mut self, _id: &RuleId, _position: &InputPosition) {}
+
+    // The span is empty when the expression fails.
+    fn attempt_expression(&mut self, _expr: &Expression, _span: &Span, _matched: bool) {}
+}
+
+// Writes an indented trace of parsing. Write errors are ignored.
+pub struct TraceObserver<W: Write> {
+    writer: W,
+    depth: usize,
+}
+
+impl<W: Write> TraceObserver<W> {
+    pub fn new(writer: W) -> TraceObserver<W> {
+        TraceObserver {
+            writer,
+            depth: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_line(&mut self, text: String) {
+        let _ = writeln!(self.writer, "{}{}", "  ".repeat(self.depth), text);
+    }
+}
+
+impl<W: Write> ParseObserver for TraceObserver<W> {
+    fn enter_rule(&mut self, id: &RuleId, position: &InputPosition) {
+        self.write_line(format!("{} at {}", id, position));
+        self.depth += 1;
+    }
+
+    fn succeed_rule(&mut self, id: &RuleId, span: &Span) {
+        self.depth -= 1;
+        self.write_line(format!("{} matched {}-{}", id, span.start, span.end));
+    }
+
+    fn fail_rule(&mut self, id: &RuleId, position: &InputPosition) {
+        self.depth -= 1;
+        self.write_line(format!("{} failed at {}", id, position));
+    }
+
+    fn attempt_expression(&mut self, expr: &Expression, span: &Span, matched: bool) {
+        if matched {
+            self.write_line(format!("{} matched {}-{}", expr, span.start, span.end));
+        } else {
+            self.write_line(format!("{} failed at {}", expr, span.start));
+        }
+    }
+}
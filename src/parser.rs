@@ -1,126 +1,861 @@
 use {
+    std::collections::{BTreeMap, HashMap},
+    std::rc::Rc,
+    std::fmt::{self, Display, Formatter},
     regex::Regex,
     crate::{
         *,
+        observer::ParseObserver,
         tree::*,
     }
 };
 
+// Indexes are byte offsets placed on character boundaries for text and token indexes for token streams.
 pub trait ParserInput {
+    type Counter: PositionCounter;
+
     fn count(&self) -> usize;
 
-    fn slice(&self, skip: usize, take: usize) -> String;
+    fn position_counter(&self) -> Self::Counter;
+
+    // Text of the unit at the index and the index after it. Units are characters of text or tokens.
+    fn next_unit(&self, index: usize) -> Option<(&str, usize)>;
+
+    // Index after the string if the input at the index starts with it.
+    fn starts_with(&self, index: usize, s: &str) -> Option<usize>;
+
+    // Kind of the unit at the index. Text has no kinds.
+    fn kind(&self, _index: usize) -> Option<&str> {
+        None
+    }
+
+    fn text(&self, start: usize, end: usize) -> String;
+}
+
+pub trait PositionCounter {
+    fn get_position(&self, index: usize) -> InputPosition;
+
+    // Position where input before the index ends. It differs from the start position of the index when units have gaps between them.
+    fn get_end_position(&self, index: usize) -> InputPosition {
+        self.get_position(index)
+    }
+
+    // Spans of tokens end at the end of the last token rather than the start of the next one. Empty spans stay at the start.
+    fn get_span(&self, start_index: usize, end_index: usize) -> Span {
+        let end = if end_index > start_index {
+            self.get_end_position(end_index)
+        } else {
+            self.get_position(end_index)
+        };
+
+        Span::new(self.get_position(start_index), end)
+    }
+
+    // Converts the index of a position into an input index.
+    fn get_input_index(&self, position_index: usize) -> usize;
+}
+
+impl PositionCounter for InputPositionCounter {
+    fn get_position(&self, index: usize) -> InputPosition {
+        InputPositionCounter::get_position(self, index)
+    }
+
+    fn get_input_index(&self, position_index: usize) -> usize {
+        self.get_offset(position_index)
+    }
+}
+
+// Spans of tokens. The index after the last token is positioned at its end.
+impl PositionCounter for Vec<Span> {
+    fn get_position(&self, index: usize) -> InputPosition {
+        match self.get(index) {
+            Some(span) => span.start.clone(),
+            None => self.get_end_position(index),
+        }
+    }
+
+    fn get_end_position(&self, index: usize) -> InputPosition {
+        match index.checked_sub(1).and_then(|prev_index| self.get(prev_index)) {
+            Some(span) => span.end.clone(),
+            None => InputPosition::new(0, 0, 0),
+        }
+    }
+
+    fn get_input_index(&self, position_index: usize) -> usize {
+        self.partition_point(|span| span.start.index < position_index)
+    }
 }
 
 impl ParserInput for str {
+    type Counter = InputPositionCounter;
+
     fn count(&self) -> usize {
-        self.chars().count()
+        self.len()
+    }
+
+    fn position_counter(&self) -> InputPositionCounter {
+        InputPositionCounter::from(self)
+    }
+
+    fn next_unit(&self, index: usize) -> Option<(&str, usize)> {
+        let end = index + self[index..].chars().next()?.len_utf8();
+        Some((&self[index..end], end))
+    }
+
+    fn starts_with(&self, index: usize, s: &str) -> Option<usize> {
+        self[index..].starts_with(s).then(|| index + s.len())
     }
 
-    fn slice(&self, skip: usize, take: usize) -> String {
-        self.chars().skip(skip).take(take).collect::<String>()
+    fn text(&self, start: usize, end: usize) -> String {
+        self[start..end].to_string()
+    }
+}
+
+// Token of pre-lexed input. The span is used as the position in the source.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: String,
+    pub text: String,
+    pub span: Span,
+}
+
+impl Token {
+    pub fn new(kind: &str, text: &str, span: Span) -> Token {
+        Token {
+            kind: kind.to_string(),
+            text: text.to_string(),
+            span,
+        }
+    }
+}
+
+// Strings and character classes match whole token texts.
+impl ParserInput for [Token] {
+    type Counter = Vec<Span>;
+
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn position_counter(&self) -> Vec<Span> {
+        self.iter().map(|token| token.span.clone()).collect()
+    }
+
+    fn next_unit(&self, index: usize) -> Option<(&str, usize)> {
+        self.get(index).map(|token| (token.text.as_str(), index + 1))
+    }
+
+    fn starts_with(&self, index: usize, s: &str) -> Option<usize> {
+        self.get(index).filter(|token| token.text == s).map(|_| index + 1)
+    }
+
+    fn kind(&self, index: usize) -> Option<&str> {
+        self.get(index).map(|token| token.kind.as_str())
+    }
+
+    fn text(&self, start: usize, end: usize) -> String {
+        self[start..end].iter().map(|token| token.text.as_str()).collect()
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ParserError {
-    NoMatchedRule,
+    UnexpectedInput { position: InputPosition, expected: Vec<Expression> },
     RuleNotExists { id: RuleId },
-    ExceededMaxRecursion,
+    ExceededMaxRecursion { max_recursion: usize },
+    InvalidTextEdit { start: usize, end: usize },
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::UnexpectedInput { position, expected } if expected.is_empty() => write!(f, "unexpected input at {}", position),
+            ParserError::UnexpectedInput { position, expected } => {
+                let expected = expected.iter().map(|expr| expr.to_string()).collect::<Vec<String>>();
+                write!(f, "unexpected input at {}, expected {}", position, expected.join(", "))
+            },
+            ParserError::RuleNotExists { id } => write!(f, "rule `{}` does not exist", id),
+            ParserError::ExceededMaxRecursion { max_recursion } => write!(f, "exceeded max recursion of {}", max_recursion),
+            ParserError::InvalidTextEdit { start, end } => write!(f, "invalid text edit of range {}..{}", start, end),
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEdit {
+    // Byte offsets in the original input.
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(start: usize, end: usize, replacement: &str) -> TextEdit {
+        TextEdit {
+            start,
+            end,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    // Whether the range is ordered and placed on character boundaries of the input.
+    pub fn is_applicable(&self, input: &str) -> bool {
+        self.start <= self.end && input.is_char_boundary(self.start) && input.is_char_boundary(self.end)
+    }
+
+    // Panics if the edit isn't applicable.
+    pub fn apply(&self, input: &str) -> String {
+        format!("{}{}{}", &input[..self.start], self.replacement, &input[self.end..])
+    }
+
+    // Difference of the input length in bytes.
+    fn delta(&self) -> isize {
+        self.replacement.len() as isize - (self.end - self.start) as isize
+    }
+
+    // Difference of the input length in characters.
+    fn char_delta(&self, input: &str) -> isize {
+        self.replacement.chars().count() as isize - input[self.start..self.end].chars().count() as isize
+    }
 }
 
 pub type ParserResult = Result<SyntaxTree, ParserError>;
 pub type OptionalParserResult<'a, T> = Result<Option<T>, ParserError>;
 
-pub struct Parser<'a> {
+// Rule results cached per start index and whether trivia skipping is suppressed. Entries are ordered by start indexes to be discarded by cuts.
+type MemoTable = BTreeMap<(usize, bool), HashMap<RuleId, MemoEntry>>;
+
+struct MemoEntry {
+    // Rule node and end index.
+    result: Option<(SyntaxNode, usize)>,
+    examined_index: usize,
+    // Failures weren't reported while the entry was evaluated, so it's reused only in silence.
+    silent: bool,
+}
+
+// Columns pushed by indentation elements. Levels are shared so that the stack is restored cheaply on backtracking.
+struct IndentLevel {
+    column: usize,
+    parent: Option<Rc<IndentLevel>>,
+}
+
+// Rule nodes of the previous tree keyed by start indexes in the edited input.
+struct ReusableNodes<'a> {
+    nodes: HashMap<(usize, bool), HashMap<&'a str, &'a SyntaxNode>>,
+    edit_end: usize,
+    delta: isize,
+    // Spans are counted in characters.
+    char_delta: isize,
+}
+
+pub struct Parser<'a, I: ParserInput + ?Sized = str> {
     volt: &'a Volt,
-    input: &'a str,
+    input: &'a I,
     index: usize,
-    counter: InputPositionCounter,
+    counter: I::Counter,
+    memo: MemoTable,
+    memoization: bool,
+    reusable: Option<ReusableNodes<'a>>,
+    examined_index: usize,
+    furthest_index: usize,
+    expected: Vec<Expression>,
+    // Failures aren't reported inside negative lookahead or trivia.
+    silence: usize,
     pub(crate) recursion: usize,
+    // Trivia rule of the current rule's module.
+    trivia: Option<&'a RuleId>,
+    lexical: usize,
+    // Whether a cut has passed in the current alternative.
+    cut: bool,
+    // Indexes which enclosing choices, loops, lookaheads and left recursion may return to. Points committed by cuts are `usize::MAX`.
+    backtrack_points: Vec<usize>,
+    // Backtrack point of the innermost cut scope.
+    cut_point: Option<usize>,
+    // Names and input captured in the current rule. Later captures shadow earlier ones.
+    captures: Vec<(String, String)>,
+    // Indentation stack whose bottom is column 0.
+    indent_level: Option<Rc<IndentLevel>>,
+    // Whether the current rule has used the indentation stack. Results of such rules aren't memoized or reused.
+    indentation_dependent: bool,
+    observer: Option<&'a mut dyn ParseObserver>,
+}
+
+impl<'a> Parser<'a> {
+    // Reuses rule nodes of the previous tree whose examined input isn't changed by the edit.
+    pub fn reparse(volt: &'a Volt, tree: &'a SyntaxTree, input: &str, edit: &TextEdit, entry_rule_id: &RuleId) -> ParserResult {
+        if !edit.is_applicable(input) {
+            return Err(ParserError::InvalidTextEdit { start: edit.start, end: edit.end });
+        }
+
+        let new_input = edit.apply(input);
+        let mut parser = Parser::new(volt, new_input.as_str(), None);
+        parser.memoization = true;
+
+        let mut reusable = ReusableNodes {
+            nodes: HashMap::new(),
+            edit_end: edit.end,
+            delta: edit.delta(),
+            char_delta: edit.char_delta(input),
+        };
+
+        reusable.collect(&tree.root, edit);
+        parser.reusable = Some(reusable);
+
+        match parser.parse_entry(entry_rule_id) {
+            Ok(tree) => Ok(tree),
+            // Failures inside reused nodes aren't reported, so errors are taken from a full parse.
+            Err(_) => Parser::parse(volt, new_input.as_str(), entry_rule_id),
+        }
+    }
 }
 
+#[cfg(test)]
 impl<'a> Parser<'a> {
-    pub fn parse(volt: &'a Volt, input: &str, entry_rule_id: &RuleId) -> ParserResult {
-        let mut parser = Parser {
+    // Number of memo entries left after parsing the input.
+    pub(crate) fn count_memo_entries(volt: &'a Volt, input: &'a str, entry_rule_id: &RuleId) -> usize {
+        let mut parser = Parser::new(volt, input, None);
+        assert!(matches!(parser.rule(entry_rule_id), Ok(Some(_))));
+        parser.memo.values().map(|rules| rules.len()).sum()
+    }
+}
+
+impl<'a, I: ParserInput + ?Sized> Parser<'a, I> {
+    pub fn parse(volt: &'a Volt, input: &'a I, entry_rule_id: &RuleId) -> ParserResult {
+        Parser::run(volt, input, entry_rule_id, None)
+    }
+
+    pub fn parse_with_observer(volt: &'a Volt, input: &'a I, entry_rule_id: &RuleId, observer: &'a mut dyn ParseObserver) -> ParserResult {
+        Parser::run(volt, input, entry_rule_id, Some(observer))
+    }
+
+    fn run(volt: &'a Volt, input: &'a I, entry_rule_id: &RuleId, observer: Option<&'a mut dyn ParseObserver>) -> ParserResult {
+        Parser::new(volt, input, observer).parse_entry(entry_rule_id)
+    }
+
+    fn new(volt: &'a Volt, input: &'a I, observer: Option<&'a mut dyn ParseObserver>) -> Parser<'a, I> {
+        Parser {
             volt,
             input,
             index: 0,
-            counter: InputPositionCounter::from(input),
+            counter: input.position_counter(),
+            memo: BTreeMap::new(),
+            memoization: volt.memoization,
+            reusable: None,
+            examined_index: 0,
+            furthest_index: 0,
+            expected: Vec::new(),
+            silence: 0,
             recursion: 0,
+            trivia: None,
+            lexical: 0,
+            cut: false,
+            backtrack_points: Vec::new(),
+            cut_point: None,
+            captures: Vec::new(),
+            indent_level: None,
+            indentation_dependent: false,
+            observer,
+        }
+    }
+
+    fn parse_entry(mut self, entry_rule_id: &RuleId) -> ParserResult {
+        // Syntactic entry rules also accept trivia around the input.
+        self.trivia = self.module_trivia(entry_rule_id);
+        let mut children = self.skip_trivia()?;
+
+        let mut root = match self.rule(entry_rule_id)? {
+            Some(v) => v,
+            None => return Err(self.unexpected_input()),
         };
 
-        match parser.rule(entry_rule_id)? {
-            Some(root) if parser.index == parser.input.count() => Ok(SyntaxTree::new(root)),
-            _ => Err(ParserError::NoMatchedRule),
+        let mut trailing_trivia = self.skip_trivia()?;
+
+        if self.index != self.input.count() {
+            return Err(self.unexpected_input());
+        }
+
+        if !children.is_empty() || !trailing_trivia.is_empty() {
+            children.append(&mut root.children);
+            children.append(&mut trailing_trivia);
+            root.children = children;
+            root.span = self.span(0);
+            // The root no longer matches the rule result.
+            root.application = None;
+        }
+
+        if self.volt.lossless {
+            attach_trivia(&mut root.children);
+        }
+
+        Ok(SyntaxTree::new(root))
+    }
+
+    // Span from the start index to the current index.
+    fn span(&self, start_index: usize) -> Span {
+        self.counter.get_span(start_index, self.index)
+    }
+
+    fn unexpected_input(&self) -> ParserError {
+        let (index, expected) = if self.index > self.furthest_index {
+            (self.index, Vec::new())
+        } else {
+            (self.furthest_index, self.expected.clone())
+        };
+
+        ParserError::UnexpectedInput {
+            position: self.counter.get_position(index),
+            expected,
+        }
+    }
+
+    // Records an expression which failed at the index, keeping only ones at the furthest index.
+    fn expect(&mut self, index: usize, expr: &Expression) {
+        if self.silence > 0 || index < self.furthest_index {
+            return;
+        }
+
+        if index > self.furthest_index {
+            self.furthest_index = index;
+            self.expected.clear();
+        }
+
+        if !self.expected.contains(expr) {
+            self.expected.push(expr.clone());
         }
     }
 
     fn rule(&mut self, rule_id: &RuleId) -> OptionalParserResult<SyntaxNode> {
+        let start_index = self.index;
+        let (tmp_furthest_index, tmp_expected_len) = (self.furthest_index, self.expected.len());
+
+        if let Some(observer) = &mut self.observer {
+            observer.enter_rule(rule_id, &self.counter.get_position(start_index));
+        }
+
+        let tmp_examined_index = std::mem::replace(&mut self.examined_index, start_index);
+        let tmp_indentation_dependent = std::mem::replace(&mut self.indentation_dependent, false);
+        let mut result = self.apply_rule(rule_id)?;
+        let examined_index = self.examined_index;
+        self.examined_index = tmp_examined_index.max(examined_index);
+        let indentation_dependent = self.indentation_dependent;
+        self.indentation_dependent |= tmp_indentation_dependent;
+
+        // Results of left recursion can be intermediate seeds, so they aren't reused.
+        if let Some(node) = &mut result {
+            let is_left_recursive = self.volt.left_recursion.is_leader(rule_id) || self.volt.left_recursion.is_member(rule_id);
+
+            node.application = (!is_left_recursive && !indentation_dependent).then_some(RuleApplication {
+                start_index,
+                end_index: self.index,
+                examined_index,
+                lexical: self.lexical > 0,
+            });
+        }
+
+        if let Some(observer) = &mut self.observer {
+            match result {
+                Some(_) => observer.succeed_rule(rule_id, &self.counter.get_span(start_index, self.index)),
+                None => observer.fail_rule(rule_id, &self.counter.get_position(start_index)),
+            }
+        }
+
+        // Failures inside a rule which didn't go beyond its start are reported by the rule name.
+        if result.is_none() && self.recursion > 0 && self.silence == 0 && self.furthest_index == start_index {
+            if tmp_furthest_index == start_index {
+                self.expected.truncate(tmp_expected_len);
+            } else {
+                self.expected.clear();
+            }
+
+            self.expect(start_index, &Expression::Rule(rule_id.clone()));
+        }
+
+        Ok(result)
+    }
+
+    fn apply_rule(&mut self, rule_id: &RuleId) -> OptionalParserResult<'_, SyntaxNode> {
+        if self.volt.left_recursion.is_leader(rule_id) {
+            return self.grow_rule(rule_id);
+        }
+
+        if !self.memoization || self.volt.left_recursion.is_member(rule_id) {
+            return self.evaluate_rule(rule_id);
+        }
+
+        if let Some(result) = self.recall(rule_id) {
+            return Ok(result);
+        }
+
+        let start_index = self.index;
+        let result = self.evaluate_rule(rule_id)?;
+
+        if !self.indentation_dependent {
+            self.memoize(rule_id, start_index, result.as_ref().map(|node| (node.clone(), self.index)));
+        }
+
+        Ok(result)
+    }
+
+    // Grows a seed of left recursion until the rule can't consume more input.
+    fn grow_rule(&mut self, rule_id: &RuleId) -> OptionalParserResult<'_, SyntaxNode> {
+        if let Some(result) = self.recall(rule_id) {
+            return Ok(result);
+        }
+
+        let start_index = self.index;
+        let mut last_result: Option<(SyntaxNode, usize)> = None;
+        self.memoize(rule_id, start_index, None);
+        // Keeps the seed while growing.
+        self.backtrack_points.push(start_index);
+
+        loop {
+            self.index = start_index;
+
+            match self.evaluate_rule(rule_id)? {
+                Some(node) if last_result.as_ref().is_none_or(|(_, end_index)| self.index > *end_index) => {
+                    last_result = Some((node, self.index));
+                    self.memoize(rule_id, start_index, last_result.clone());
+                },
+                _ => break,
+            }
+        }
+
+        self.backtrack_points.pop();
+
+        // Seeds are needed while growing, but the result depends on the indentation stack.
+        if self.indentation_dependent {
+            if let Some(rules) = self.memo.get_mut(&(start_index, self.lexical > 0)) {
+                rules.remove(rule_id);
+            }
+        }
+
+        match last_result {
+            Some((node, end_index)) => {
+                self.index = end_index;
+                Ok(Some(node))
+            },
+            None => {
+                self.index = start_index;
+                Ok(None)
+            },
+        }
+    }
+
+    fn recall(&mut self, rule_id: &RuleId) -> Option<Option<SyntaxNode>> {
+        let entry = match self.memo.get(&(self.index, self.lexical > 0)).and_then(|rules| rules.get(rule_id)) {
+            Some(v) if !v.silent || self.silence > 0 => v,
+            Some(_) => return None,
+            None => return self.reuse(rule_id),
+        };
+
+        self.examined_index = self.examined_index.max(entry.examined_index);
+
+        match &entry.result {
+            Some((node, end_index)) => {
+                self.index = *end_index;
+                Some(Some(node.clone()))
+            },
+            None => Some(None),
+        }
+    }
+
+    fn memoize(&mut self, rule_id: &RuleId, start_index: usize, result: Option<(SyntaxNode, usize)>) {
+        let entry = MemoEntry {
+            result,
+            examined_index: self.examined_index,
+            silent: self.silence > 0,
+        };
+
+        self.memo.entry((start_index, self.lexical > 0)).or_default().insert(rule_id.clone(), entry);
+    }
+
+    fn reuse(&mut self, rule_id: &RuleId) -> Option<Option<SyntaxNode>> {
+        let reusable = self.reusable.as_ref()?;
+        let node = *reusable.nodes.get(&(self.index, self.lexical > 0))?.get(rule_id.0.as_str())?;
+
+        // Lines and columns may change even if the length of the input doesn't.
+        let mut node = if node.application?.start_index >= reusable.edit_end {
+            let (delta, char_delta) = (reusable.delta, reusable.char_delta);
+            self.relocate_node(node, delta, char_delta)
+        } else {
+            node.clone()
+        };
+
+        // Trivia outside the node is attached again by the new tree.
+        if self.volt.lossless {
+            detach_outer_trivia(&mut node);
+        }
+
+        let application = node.application?;
+        self.index = application.end_index;
+        self.examined_index = self.examined_index.max(application.examined_index);
+        Some(Some(node))
+    }
+
+    // Rule applications are shifted by the byte delta and spans by the character delta.
+    fn relocate_node(&self, node: &SyntaxNode, delta: isize, char_delta: isize) -> SyntaxNode {
+        let mut relocated = SyntaxNode::new(node.name.clone(), self.relocate_span(&node.span, char_delta), self.relocate_children(&node.children, delta, char_delta));
+
+        relocated.application = node.application.map(|application| RuleApplication {
+            start_index: shift(application.start_index, delta),
+            end_index: shift(application.end_index, delta),
+            examined_index: shift(application.examined_index, delta),
+            lexical: application.lexical,
+        });
+
+        relocated
+    }
+
+    fn relocate_children(&self, children: &[SyntaxChild], delta: isize, char_delta: isize) -> Vec<SyntaxChild> {
+        children.iter().map(|each_child| match each_child {
+            SyntaxChild::Node(node) => SyntaxChild::Node(self.relocate_node(node, delta, char_delta)),
+            SyntaxChild::Leaf(leaf) => SyntaxChild::Leaf(SyntaxLeaf {
+                span: self.relocate_span(&leaf.span, char_delta),
+                ..leaf.clone()
+            }),
+            SyntaxChild::Error(error) => SyntaxChild::error(error.message.clone(), self.relocate_span(&error.span, char_delta), self.relocate_children(&error.children, delta, char_delta)),
+        }).collect()
+    }
+
+    // Recalculates lines and columns in the edited input.
+    fn relocate_span(&self, span: &Span, char_delta: isize) -> Span {
+        let relocate = |position: &InputPosition| self.counter.get_position(self.counter.get_input_index(shift(position.index, char_delta)));
+        Span::new(relocate(&span.start), relocate(&span.end))
+    }
+
+    // Input until the index affects the result of the current rule.
+    fn examine(&mut self, end_index: usize) {
+        self.examined_index = self.examined_index.max(end_index);
+    }
+
+    fn evaluate_rule(&mut self, rule_id: &RuleId) -> OptionalParserResult<'_, SyntaxNode> {
         if self.recursion >= self.volt.max_recursion {
-            return Err(ParserError::ExceededMaxRecursion);
+            return Err(ParserError::ExceededMaxRecursion { max_recursion: self.volt.max_recursion });
         }
 
         self.recursion += 1;
 
+        let trivia = self.module_trivia(rule_id);
+        let tmp_trivia = std::mem::replace(&mut self.trivia, trivia);
+        let tmp_captures = std::mem::take(&mut self.captures);
+
         let result = match self.volt.rule_map.get(rule_id) {
-            Some(elem) => if let Some(children) = self.element(elem)? { Ok(Some(SyntaxNode::new(rule_id.to_string(), children))) } else { Ok(None) },
+            Some(elem) => {
+                let start_index = self.index;
+
+                if let Some(children) = self.cut_scope(None, |parser| parser.element(elem)).0? {
+                    Ok(Some(SyntaxNode::new(rule_id.to_string(), self.span(start_index), children)))
+                } else {
+                    Ok(None)
+                }
+            },
             None => Err(ParserError::RuleNotExists { id: rule_id.clone() }),
         };
 
+        self.trivia = tmp_trivia;
+        self.captures = tmp_captures;
         self.recursion -= 1;
         result
     }
 
+    fn module_trivia(&self, rule_id: &RuleId) -> Option<&'a RuleId> {
+        let (module_name, _) = rule_id.0.rsplit_once("::")?;
+        self.volt.trivia.get(module_name)
+    }
+
+    // Returns hidden leaves of skipped trivia if it's preserved.
+    fn skip_trivia(&mut self) -> Result<Vec<SyntaxChild>, ParserError> {
+        let trivia_rule_id = match self.trivia {
+            Some(id) if self.lexical == 0 => id,
+            _ => return Ok(Vec::new()),
+        };
+
+        let start_index = self.index;
+        self.lexical += 1;
+        self.silence += 1;
+
+        let result = self.rule(trivia_rule_id);
+
+        self.lexical -= 1;
+        self.silence -= 1;
+
+        match result? {
+            Some(_) if self.volt.trivia_preservation || self.volt.lossless => Ok(self.hidden_leaves(start_index, self.index)),
+            Some(_) => Ok(Vec::new()),
+            None => {
+                self.index = start_index;
+                Ok(Vec::new())
+            },
+        }
+    }
+
+    fn hidden_leaves(&self, start_index: usize, end_index: usize) -> Vec<SyntaxChild> {
+        if start_index == end_index {
+            return Vec::new();
+        }
+
+        vec![SyntaxChild::hidden_leaf(self.counter.get_span(start_index, end_index), self.input.text(start_index, end_index))]
+    }
+
+    // Trivia followed by an element which consumes nothing is left for the next element.
+    fn element_after_trivia(&mut self, elem: &Element) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+        let mut children = self.skip_trivia()?;
+        let trivia_end_index = self.index;
+
+        match self.element(elem)? {
+            Some(mut new_children) if self.index > trivia_end_index => {
+                children.append(&mut new_children);
+                Ok(Some(children))
+            },
+            Some(new_children) => {
+                self.index = start_index;
+                Ok(Some(new_children))
+            },
+            None => {
+                self.index = start_index;
+                Ok(None)
+            },
+        }
+    }
+
     fn element(&mut self, elem: &Element) -> OptionalParserResult<Vec<SyntaxChild>> {
+        let start_index = self.index;
+        let captures_len = self.captures.len();
+        let tmp_indent_level = self.indent_level.clone();
+
         let children = match elem {
             Element::Choice(elems) => self.choice(elems)?,
             Element::Sequence(elems) => self.sequence(elems)?,
-            Element::Expression(expr) => match expr {
-                Expression::Rule(id) => if let Some(child_node) = self.rule(id)? { Some(vec![SyntaxChild::Node(child_node)]) } else { None },
-                Expression::String(s) => self.string(s)?,
-                Expression::CharacterClass(v) => self.character_class(v)?,
-                Expression::Wildcard => self.wildcard()?,
-            },
+            Element::Expression(expr) => self.expression(expr)?,
             Element::Loop(elem, range) => self.times(elem, range)?,
             Element::PositiveLookahead(elem) => self.lookahead(elem, true)?,
             Element::NegativeLookahead(elem) => self.lookahead(elem, false)?,
             Element::Error(elem, message) => match self.element(elem)? {
-                Some(children) => Some(vec![SyntaxChild::error(message.to_string(), children)]),
+                Some(children) => Some(vec![SyntaxChild::error(message.to_string(), self.span(start_index), children)]),
                 None => Some(vec![]),
             },
-            Element::Catch(elem, message) => self.element(elem)?.or(Some(vec![SyntaxChild::error(message.to_string(), vec![])])),
+            Element::Catch(elem, message) => self.element(elem)?.or_else(|| Some(vec![SyntaxChild::error(message.to_string(), self.span(start_index), vec![])])),
             Element::CatchSkip(elem, message, to) => match self.element(elem)? {
                 Some(children) => Some(children),
-                None => {
-                    while self.index <= self.input.count() {
-                        match self.element(to)? {
-                            Some(children) => return Ok(Some(vec![SyntaxChild::error(message.to_string(), children)])),
-                            None => self.index += 1,
-                        }
-                    }
+                None => loop {
+                    let skip_end_index = self.index;
 
+                    match self.element(to)? {
+                        Some(mut children) => {
+                            if self.volt.lossless {
+                                let mut skipped = self.hidden_leaves(start_index, skip_end_index);
+                                skipped.append(&mut children);
+                                children = skipped;
+                            }
+
+                            return Ok(Some(vec![SyntaxChild::error(message.to_string(), self.span(start_index), children)]));
+                        },
+                        None => match self.next_unit() {
+                            Some((_, end_index)) => self.index = end_index,
+                            None => {
+                                self.index = start_index;
+                                break None;
+                            },
+                        },
+                    }
+                },
+            },
+            Element::TreeReduction(elem, reducer) => self.element(elem)?.map(|children| {
+                // Reduced nodes may differ from rule results, so they aren't reused.
+                let mut children = reducer(children);
+                forget_applications(&mut children);
+                children
+            }),
+            Element::Predicate(elem, predicate) => match self.element(elem)? {
+                Some(children) if predicate(&children) => Some(children),
+                Some(_) => {
+                    self.index = start_index;
                     None
                 },
+                None => None,
             },
-            Element::TreeReduction(elem, reducer) => self.element(elem)?.map(|children| reducer(children)),
-            Element::Group(elem, name) => self.element(elem)?.map(|children| vec![SyntaxChild::Node(SyntaxNode::new(name.to_string(), children))]),
+            Element::Group(elem, name) => self.element(elem)?.map(|children| vec![SyntaxChild::node(name.to_string(), self.span(start_index), children)]),
             Element::Expansion(elem) => self.element(elem)?.map(|children| children.expand(0, true)),
             Element::ExpansionOnce(elem) => self.element(elem)?.map(|children| children.expand(0, false)),
             Element::Join(elem) => self.element(elem)?.map(|children| {
-                let start = if let Some(v) = children.get_start_position() {
-                    v
-                } else {
-                    InputPosition::new(usize::MAX, usize::MAX, usize::MAX)
-                };
+                if self.volt.lossless && children.iter().any(has_error) {
+                    return split_join(children);
+                }
+
+                let mut leaf = SyntaxLeaf::new(self.span(start_index), children.join_into_string());
 
-                let mut joined_children = vec![SyntaxChild::leaf(start, children.join_into_string())];
+                // Hidden input inside the joined leaf is kept as its source.
+                if self.volt.lossless {
+                    let source = children.join_into_source();
+
+                    if source != leaf.value {
+                        leaf.source = Some(source);
+                    }
+                }
+
+                let mut joined_children = vec![SyntaxChild::Leaf(leaf)];
                 joined_children.append(&mut children.eject_errors());
                 joined_children
             }),
-            Element::Hidden(elem) => self.element(elem)?.map(|_| vec![]),
+            Element::Hidden(elem) => match self.element(elem)? {
+                Some(_) if self.volt.lossless => Some(self.hidden_leaves(start_index, self.index)),
+                Some(_) => Some(vec![]),
+                None => None,
+            },
+            Element::Lexical(elem) => {
+                self.lexical += 1;
+                let result = self.element(elem);
+                self.lexical -= 1;
+                result?
+            },
+            Element::Precedence(atom, levels) => self.precedence(atom, levels, 0)?,
+            Element::Cut => {
+                self.commit_cut();
+                Some(Vec::new())
+            },
+            Element::Throw(label) => self.throw(label)?,
+            Element::Indentation(indentation) => self.indentation(indentation),
+            Element::Capture(elem, name) => match self.element(elem)? {
+                Some(children) => {
+                    self.captures.push((name.to_string(), self.input.text(start_index, self.index)));
+                    Some(children)
+                },
+                None => None,
+            },
+        };
+
+        // Captures and indentation inside failed elements are discarded on backtracking.
+        if children.is_none() {
+            self.captures.truncate(captures_len);
+            self.indent_level = tmp_indent_level;
+        }
+
+        Ok(children)
+    }
+
+    fn expression(&mut self, expr: &Expression) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+
+        let children = match expr {
+            Expression::Rule(id) => return Ok(self.rule(id)?.map(|child_node| vec![SyntaxChild::Node(child_node)])),
+            Expression::String(s) => self.string(s)?,
+            Expression::CharacterClass(v) => self.character_class(v)?,
+            Expression::Wildcard => self.wildcard()?,
+            Expression::Token(kind) => self.token(kind)?,
+            Expression::Backreference(name) => self.backreference(name)?,
         };
 
+        if children.is_none() {
+            self.expect(start_index, expr);
+        }
+
+        if let Some(observer) = &mut self.observer {
+            observer.attempt_expression(expr, &self.counter.get_span(start_index, self.index), children.is_some());
+        }
+
         Ok(children)
     }
 
@@ -128,22 +863,84 @@ impl<'a> Parser<'a> {
         let tmp_index = self.index;
 
         for each_elem in elems {
-            if let Some(children) = self.element(each_elem)? {
+            let (result, has_cut) = self.cut_scope(Some(tmp_index), |parser| parser.element(each_elem));
+
+            if let Some(children) = result? {
                 return Ok(Some(children));
             } else {
                 self.index = tmp_index;
+
+                // Alternatives after a cut aren't tried.
+                if has_cut {
+                    break;
+                }
             }
         }
 
         Ok(None)
     }
 
-    fn sequence(&mut self, elems: &Vec<Element>) -> OptionalParserResult<Vec<SyntaxChild>> {
+    // Labels without recovery generate errors without skipping input. Recovery which doesn't match is an ordinary failure caught by choices.
+    fn throw(&mut self, label: &str) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+
+        let children = match self.volt.recovery.get(label) {
+            Some(recovery) => match self.element(recovery)? {
+                Some(children) => children,
+                None => return Ok(None),
+            },
+            None => Vec::new(),
+        };
+
+        Ok(Some(vec![SyntaxChild::error(label.to_string(), self.span(start_index), children)]))
+    }
+
+    // Cuts inside the scope don't affect alternatives outside it. Returns whether a cut has passed.
+    // The backtrack index is where the scope returns on failure, and it's no longer returned to once a cut passes.
+    fn cut_scope<T>(&mut self, backtrack_index: Option<usize>, f: impl FnOnce(&mut Self) -> T) -> (T, bool) {
+        let tmp_cut = std::mem::replace(&mut self.cut, false);
+
+        let cut_point = backtrack_index.map(|index| {
+            self.backtrack_points.push(index);
+            self.backtrack_points.len() - 1
+        });
+
+        let tmp_cut_point = std::mem::replace(&mut self.cut_point, cut_point);
+        let result = f(self);
+
+        if cut_point.is_some() {
+            self.backtrack_points.pop();
+        }
+
+        self.cut_point = tmp_cut_point;
+        (result, std::mem::replace(&mut self.cut, tmp_cut))
+    }
+
+    // Discards memo entries before every index that enclosing scopes may return to since they can't be recalled anymore.
+    // Memo is a cache, so backtracking which isn't tracked by the points only costs re-evaluation.
+    fn commit_cut(&mut self) {
+        self.cut = true;
+
+        if let Some(cut_point) = self.cut_point {
+            self.backtrack_points[cut_point] = usize::MAX;
+        }
+
+        let discard_end = self.backtrack_points.iter().copied().fold(self.index, usize::min);
+        self.memo = self.memo.split_off(&(discard_end, false));
+    }
+
+    fn sequence(&mut self, elems: &[Element]) -> OptionalParserResult<Vec<SyntaxChild>> {
         let tmp_index = self.index;
         let mut children = Vec::new();
 
-        for each_elem in elems {
-            match self.element(each_elem)? {
+        for (i, each_elem) in elems.iter().enumerate() {
+            let result = if i == 0 {
+                self.element(each_elem)?
+            } else {
+                self.element_after_trivia(each_elem)?
+            };
+
+            match result {
                 Some(mut new_children) => children.append(&mut new_children),
                 None => {
                     self.index = tmp_index;
@@ -162,11 +959,24 @@ impl<'a> Parser<'a> {
             let tmp_index = self.index;
             let mut children = Vec::new();
             let mut count = 0;
+            let skips_trivia = elem.skips_trivia_between_iterations();
 
             loop {
-                let mut new_children = match self.element(elem) {
+                let (result, has_cut) = self.cut_scope(Some(self.index), |parser| if count == 0 || !skips_trivia {
+                    parser.element(elem)
+                } else {
+                    parser.element_after_trivia(elem)
+                });
+
+                let mut new_children = match result {
                     Ok(option) => match option {
                         Some(new_children) => new_children,
+                        // An iteration failing after a cut fails the loop and commits the enclosing choice.
+                        None if has_cut => {
+                            self.index = tmp_index;
+                            self.cut = true;
+                            return Ok(None);
+                        },
                         _ => break,
                     },
                     Err(e) => return Err(e),
@@ -201,13 +1011,131 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Parses operators by precedence climbing. Prefix operators can appear regardless of the minimum level.
+    fn precedence(&mut self, atom: &Element, levels: &[PrecedenceLevel], min_level: usize) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+
+        let mut lhs = match self.prefix_operation(atom, levels)? {
+            Some(children) => children,
+            None => match self.element(atom)? {
+                Some(children) => children,
+                None => return Ok(None),
+            },
+        };
+
+        'operators: loop {
+            // Tighter levels are tried first so that their operators aren't shadowed by shorter ones of looser levels.
+            for (level_index, each_level) in levels.iter().enumerate().skip(min_level).rev() {
+                let operand_min_level = match each_level.associativity {
+                    Associativity::Left => Some(level_index + 1),
+                    Associativity::Right => Some(level_index),
+                    Associativity::Postfix => None,
+                    Associativity::Prefix => continue,
+                };
+
+                for each_operator in &each_level.operators {
+                    let operator_index = self.index;
+
+                    let mut operator = match self.element_after_trivia(each_operator)? {
+                        Some(children) => children,
+                        None => {
+                            self.index = operator_index;
+                            continue;
+                        },
+                    };
+
+                    let (name, mut operand) = match operand_min_level {
+                        Some(min_level) => match self.operand(atom, levels, min_level)? {
+                            Some(children) => ("binary", children),
+                            None => {
+                                self.index = operator_index;
+                                continue;
+                            },
+                        },
+                        None => ("postfix", Vec::new()),
+                    };
+
+                    // Operations which consume nothing would repeat forever.
+                    if self.index == operator_index {
+                        break 'operators;
+                    }
+
+                    let mut children = lhs;
+                    children.append(&mut operator);
+                    children.append(&mut operand);
+                    lhs = vec![SyntaxChild::node(name.to_string(), self.span(start_index), children)];
+                    continue 'operators;
+                }
+            }
+
+            break;
+        }
+
+        Ok(Some(lhs))
+    }
+
+    fn prefix_operation(&mut self, atom: &Element, levels: &[PrecedenceLevel]) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+
+        for (level_index, each_level) in levels.iter().enumerate().rev() {
+            if each_level.associativity != Associativity::Prefix {
+                continue;
+            }
+
+            for each_operator in &each_level.operators {
+                if let Some(mut children) = self.element(each_operator)? {
+                    if self.index != start_index {
+                        if let Some(mut operand) = self.operand(atom, levels, level_index + 1)? {
+                            children.append(&mut operand);
+                            return Ok(Some(vec![SyntaxChild::node("prefix".to_string(), self.span(start_index), children)]));
+                        }
+                    }
+                }
+
+                self.index = start_index;
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn operand(&mut self, atom: &Element, levels: &[PrecedenceLevel], min_level: usize) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+        let mut children = self.skip_trivia()?;
+
+        match self.precedence(atom, levels, min_level)? {
+            Some(mut new_children) => {
+                children.append(&mut new_children);
+                Ok(Some(children))
+            },
+            None => {
+                self.index = start_index;
+                Ok(None)
+            },
+        }
+    }
+
     fn lookahead(&mut self, elem: &Element, is_positive: bool) -> OptionalParserResult<Vec<SyntaxChild>> {
-        let tmp_index = self.index;
-        let result = self.element(elem);
+        let (tmp_index, tmp_indent_level) = (self.index, self.indent_level.clone());
+
+        // Failures inside negative lookahead are expected, so they aren't reported.
+        if !is_positive {
+            self.silence += 1;
+        }
+
+        // Input is examined again after lookahead.
+        self.backtrack_points.push(tmp_index);
+        let (result, _) = self.cut_scope(None, |parser| parser.element(elem));
+        self.backtrack_points.pop();
+
+        if !is_positive {
+            self.silence -= 1;
+        }
 
         match result {
             Ok(option) => {
                 self.index = tmp_index;
+                self.indent_level = tmp_indent_level;
 
                 let has_succeeded = if is_positive {
                     option.is_some()
@@ -225,42 +1153,300 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn next_unit(&mut self) -> Option<(&'a str, usize)> {
+        let unit = self.input.next_unit(self.index);
+        self.examine(unit.map_or(self.index + 1, |(_, end_index)| end_index));
+        unit
+    }
+
+    fn leaf(&mut self, end_index: usize, value: String) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        let start_index = self.index;
+        self.index = end_index;
+        Ok(Some(vec![SyntaxChild::leaf(self.span(start_index), value)]))
+    }
+
     fn string(&mut self, s: &str) -> OptionalParserResult<Vec<SyntaxChild>> {
-        if self.input.count() >= self.index + s.count() && self.input.slice(self.index, s.count()) == *s {
-            let start_index = self.index;
-            self.index += s.count();
-            Ok(Some(vec![SyntaxChild::leaf(self.counter.get_position(start_index), s.to_string())]))
-        } else {
-            Ok(None)
+        self.examine(self.index + s.len());
+
+        match self.input.starts_with(self.index, s) {
+            Some(end_index) => self.leaf(end_index, s.to_string()),
+            None => Ok(None),
         }
     }
 
+    // Character classes match whole units.
     fn character_class(&mut self, regex: &Regex) -> OptionalParserResult<Vec<SyntaxChild>> {
-        if self.input.count() >= self.index + 1 {
-            // todo: optimization
-            let target = self.input.slice(self.index, 1);
-
-            match regex.find(&target) {
-                Some(regex_match) if regex_match.start() == 0 => {
-                    let start_index = self.index;
-                    self.index += 1;
-                    Ok(Some(vec![SyntaxChild::leaf(self.counter.get_position(start_index), target)]))
-                },
+        match self.next_unit() {
+            Some((target, end_index)) => match regex.find(target) {
+                Some(regex_match) if regex_match.start() == 0 && regex_match.end() == target.len() => self.leaf(end_index, target.to_string()),
                 _ => Ok(None),
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn wildcard(&mut self) -> OptionalParserResult<Vec<SyntaxChild>> {
+        match self.next_unit() {
+            Some((target, end_index)) => self.leaf(end_index, target.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    // Consumes blanks before the compared input. The end of input is at column 0.
+    fn indentation(&mut self, indentation: &Indentation) -> Option<Vec<SyntaxChild>> {
+        self.indentation_dependent = true;
+        let start_index = self.index;
+
+        while let Some((text, end_index)) = self.next_unit() {
+            if text.is_empty() || !text.chars().all(|ch| ch == ' ' || ch == '\t') {
+                break;
             }
+
+            self.index = end_index;
+        }
+
+        let column = match self.input.next_unit(self.index) {
+            Some(_) => self.counter.get_position(self.index).column,
+            None => 0,
+        };
+
+        let top_column = self.indent_level.as_ref().map_or(0, |level| level.column);
+
+        let has_matched = match indentation {
+            Indentation::Indent if column > top_column => {
+                self.indent_level = Some(Rc::new(IndentLevel { column, parent: self.indent_level.take() }));
+                true
+            },
+            Indentation::Dedent if column < top_column => {
+                self.indent_level = self.indent_level.as_ref().and_then(|level| level.parent.clone());
+                true
+            },
+            Indentation::Same => column == top_column,
+            _ => false,
+        };
+
+        if !has_matched {
+            self.index = start_index;
+            None
+        } else if self.volt.lossless {
+            Some(self.hidden_leaves(start_index, self.index))
         } else {
-            Ok(None)
+            Some(Vec::new())
         }
     }
 
-    fn wildcard(&mut self) -> OptionalParserResult<Vec<SyntaxChild>> {
-        if self.input.count() >= self.index + 1 {
-            let s = self.input.slice(self.index, 1);
-            let start_index = self.index;
-            self.index += 1;
-            Ok(Some(vec![SyntaxChild::leaf(self.counter.get_position(start_index), s)]))
+    fn backreference(&mut self, name: &str) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        match self.captures.iter().rev().find(|(each_name, _)| each_name == name) {
+            Some((_, text)) => self.string(&text.clone()),
+            None => Ok(None),
+        }
+    }
+
+    fn token(&mut self, kind: &str) -> OptionalParserResult<'_, Vec<SyntaxChild>> {
+        match self.next_unit() {
+            Some((target, end_index)) if self.input.kind(self.index) == Some(kind) => self.leaf(end_index, target.to_string()),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<'a> ReusableNodes<'a> {
+    fn collect(&mut self, node: &'a SyntaxNode, edit: &TextEdit) {
+        if let Some(application) = node.application {
+            let start_index = application.start_index;
+
+            let new_start_index = if start_index >= edit.end {
+                Some(shift(start_index, self.delta))
+            } else if application.examined_index <= edit.start {
+                Some(start_index)
+            } else {
+                None
+            };
+
+            if let Some(new_start_index) = new_start_index {
+                self.nodes.entry((new_start_index, application.lexical)).or_default().insert(&node.name, node);
+            }
+        }
+
+        self.collect_children(&node.children, edit);
+    }
+
+    fn collect_children(&mut self, children: &'a [SyntaxChild], edit: &TextEdit) {
+        for each_child in children {
+            match each_child {
+                SyntaxChild::Node(node) => self.collect(node, edit),
+                SyntaxChild::Error(error) => self.collect_children(&error.children, edit),
+                SyntaxChild::Leaf(_) => (),
+            }
+        }
+    }
+}
+
+fn shift(index: usize, delta: isize) -> usize {
+    index.checked_add_signed(delta).unwrap()
+}
+
+// Attaches hidden leaves to adjacent leaves as trivia in lossless mode, preferring the following leaf.
+// Hidden input of a node is attached inside the node, and hidden leaves without adjacent leaves such as ones in nodes without leaves are kept.
+fn attach_trivia(children: &mut Vec<SyntaxChild>) {
+    for each_child in children.iter_mut() {
+        match each_child {
+            SyntaxChild::Node(node) => attach_trivia(&mut node.children),
+            SyntaxChild::Error(error) => attach_trivia(&mut error.children),
+            SyntaxChild::Leaf(_) => (),
+        }
+    }
+
+    let mut i = 0;
+
+    while i < children.len() {
+        let mut hidden = match &children[i] {
+            SyntaxChild::Leaf(leaf) if leaf.hidden => leaf.clone(),
+            _ => {
+                i += 1;
+                continue;
+            },
+        };
+
+        while let Some(SyntaxChild::Leaf(next)) = children.get(i + 1).filter(|child| child.is_hidden()) {
+            hidden.value += &next.value;
+            hidden.span.end = next.span.end.clone();
+            children.remove(i + 1);
+        }
+
+        let (preceding, following) = children.split_at_mut(i);
+
+        if let AdjacentLeaf::Leaf(leaf) = adjacent_leaf(&mut following[1..], false) {
+            leaf.leading_trivia.insert_str(0, &hidden.value);
+            children.remove(i);
+        } else if let AdjacentLeaf::Leaf(leaf) = adjacent_leaf(preceding, true) {
+            leaf.trailing_trivia += &hidden.value;
+            children.remove(i);
         } else {
-            Ok(None)
+            children[i] = SyntaxChild::Leaf(hidden);
+            i += 1;
+        }
+    }
+}
+
+enum AdjacentLeaf<'a> {
+    Leaf(&'a mut SyntaxLeaf),
+    // Hidden input is between.
+    Blocked,
+    // No input is found.
+    Empty,
+}
+
+fn adjacent_leaf(children: &mut [SyntaxChild], from_end: bool) -> AdjacentLeaf<'_> {
+    let children: Box<dyn Iterator<Item = &mut SyntaxChild>> = if from_end {
+        Box::new(children.iter_mut().rev())
+    } else {
+        Box::new(children.iter_mut())
+    };
+
+    for each_child in children {
+        let adjacent = match each_child {
+            SyntaxChild::Node(node) => adjacent_leaf(&mut node.children, from_end),
+            SyntaxChild::Error(error) => adjacent_leaf(&mut error.children, from_end),
+            SyntaxChild::Leaf(leaf) if leaf.hidden => AdjacentLeaf::Blocked,
+            SyntaxChild::Leaf(leaf) => AdjacentLeaf::Leaf(leaf),
+        };
+
+        if !matches!(adjacent, AdjacentLeaf::Empty) {
+            return adjacent;
+        }
+    }
+
+    AdjacentLeaf::Empty
+}
+
+// Removes trivia attached by ancestors from the first and last leaves of the node. Trivia inside the span is kept.
+fn detach_outer_trivia(node: &mut SyntaxNode) {
+    let (start, end) = (node.span.start.index, node.span.end.index);
+
+    if let AdjacentLeaf::Leaf(leaf) = adjacent_leaf(&mut node.children, false) {
+        let inner_len = leaf.span.start.index.saturating_sub(start);
+        let outer_len = leaf.leading_trivia.chars().count().saturating_sub(inner_len);
+        leaf.leading_trivia = leaf.leading_trivia.chars().skip(outer_len).collect();
+    }
+
+    if let AdjacentLeaf::Leaf(leaf) = adjacent_leaf(&mut node.children, true) {
+        let inner_len = end.saturating_sub(leaf.span.end.index);
+        leaf.trailing_trivia = leaf.trailing_trivia.chars().take(inner_len).collect();
+    }
+}
+
+fn has_error(child: &SyntaxChild) -> bool {
+    match child {
+        SyntaxChild::Node(node) => node.children.iter().any(has_error),
+        SyntaxChild::Leaf(_) => false,
+        SyntaxChild::Error(_) => true,
+    }
+}
+
+// Joins leaves between errors separately in lossless mode so that errors stay where they occurred in the input.
+fn split_join(children: Vec<SyntaxChild>) -> Vec<SyntaxChild> {
+    let mut flattened = Vec::new();
+    flatten_nodes(children, &mut flattened);
+
+    let mut joined = Vec::new();
+    let mut leaves = Vec::new();
+
+    for each_child in flattened {
+        match each_child {
+            SyntaxChild::Error(_) => {
+                joined.append(&mut join_leaves(std::mem::take(&mut leaves)));
+                joined.push(each_child);
+            },
+            _ => leaves.push(each_child),
+        }
+    }
+
+    joined.append(&mut join_leaves(leaves));
+    joined
+}
+
+fn flatten_nodes(children: Vec<SyntaxChild>, flattened: &mut Vec<SyntaxChild>) {
+    for each_child in children {
+        match each_child {
+            SyntaxChild::Node(node) => flatten_nodes(node.children, flattened),
+            _ => flattened.push(each_child),
+        }
+    }
+}
+
+// Leaves only with hidden input are kept to be attached as trivia.
+fn join_leaves(leaves: Vec<SyntaxChild>) -> Vec<SyntaxChild> {
+    let value = leaves.join_into_string();
+
+    if value.is_empty() {
+        return leaves;
+    }
+
+    let span = match (leaves.first(), leaves.last()) {
+        (Some(SyntaxChild::Leaf(first)), Some(SyntaxChild::Leaf(last))) => Span::new(first.span.start.clone(), last.span.end.clone()),
+        _ => unreachable!("joined children should be leaves"),
+    };
+
+    let mut leaf = SyntaxLeaf::new(span, value);
+    let source = leaves.join_into_source();
+
+    if source != leaf.value {
+        leaf.source = Some(source);
+    }
+
+    vec![SyntaxChild::Leaf(leaf)]
+}
+
+fn forget_applications(children: &mut [SyntaxChild]) {
+    for each_child in children {
+        match each_child {
+            SyntaxChild::Node(node) => {
+                node.application = None;
+                forget_applications(&mut node.children);
+            },
+            SyntaxChild::Error(error) => forget_applications(&mut error.children),
+            SyntaxChild::Leaf(_) => (),
         }
     }
 }
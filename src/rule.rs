@@ -26,11 +26,9 @@ impl Rule {
         }
     }
 
+    // Left recursion is resolved by the parser, so rules are accepted as they are.
+    #[deprecated(note = "left recursion is supported by the parser, so rules don't need to be checked")]
     pub fn detect_left_recursion(self) -> Rule {
-        if self.element.has_left_recursion(&self.id) {
-            panic!("Left recursion detected at rule definition of `{}`.", self.id);
-        }
-
         self
     }
 }
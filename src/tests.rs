@@ -1,2 +1,17 @@
+mod capture;
+mod diagnostic;
+mod grammar;
+mod incremental;
+mod indentation;
+mod lossless;
+mod observer;
 mod parser;
+mod predicate;
+mod recovery;
+#[cfg(feature = "serde")]
+mod serde;
+mod token;
+mod trivia;
 mod tree;
+mod validation;
+mod visitor;
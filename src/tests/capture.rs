@@ -0,0 +1,56 @@
+use {
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    speculate::speculate,
+};
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+
+        volt.add_grammar_str("Capture", r###"
+            raw := "r" "#"*.join.capture("hashes") "\"" (!("\"" backref("hashes")) _)*.join "\"" backref("hashes");
+            tag := "<" [a-z]+.join.capture("name") ">" (tag / [a-z])* "</" backref("name") ">";
+            outer := [a-z].capture("c") inner;
+            inner := backref("c");
+            backtrack := ([a-z].capture("c") "!" / [a-z]) backref("c");
+        "###).unwrap();
+
+        #[allow(unused)]
+        let parse = |input: &str, rule_name: &str| volt.parse(input, &RuleId(format!("Capture::{}", rule_name)));
+    }
+
+    describe "capture and backreference" {
+        it "matches captured input" {
+            assert_eq!(parse(r###"r##"a"#b"##"###, "raw").unwrap().to_sexp(), r###"(Capture::raw "r" "##" "\"" "a\"#b" "\"" "##")"###);
+            assert_eq!(parse(r#"r"a""#, "raw").unwrap().to_sexp(), r#"(Capture::raw "r" "" "\"" "a" "\"" "")"#);
+            assert!(parse(r###"r##"a"#"###, "raw").is_err());
+        }
+
+        it "shadows captures by nested rules" {
+            assert!(parse("<a><b>x</b>y</a>", "tag").is_ok());
+            assert!(parse("<a><b>x</a></b>", "tag").is_err());
+        }
+
+        it "reports expected backreference" {
+            assert_eq!(parse("<a>x</b>", "tag"), Err(ParserError::UnexpectedInput {
+                position: pos!(6, 0, 6),
+                expected: vec![Expression::Backreference("name".to_string())],
+            }));
+        }
+
+        it "doesn't refer to captures of other rules" {
+            assert!(parse("aa", "outer").is_err());
+        }
+
+        it "discards captures on backtracking" {
+            assert!(parse("a!a", "backtrack").is_ok());
+            assert!(parse("aa", "backtrack").is_err());
+        }
+
+        it "displays capture and backreference" {
+            assert_eq!(seq![wildcard().capture("c"), backref("c")].to_string(), "(_.capture(\"c\") backref(\"c\"))");
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use {
+    crate::*,
+    crate::diagnostic::*,
+    crate::parser::*,
+    crate::tree::*,
+    speculate::speculate,
+};
+
+speculate!{
+    describe "diagnostic" {
+        it "collects syntax errors in tree" {
+            let tree = tree!(
+                node!("root" => [
+                    SyntaxChild::error("outer".to_string(), span!(pos!(0, 0, 0), pos!(2, 0, 2)), vec![
+                        SyntaxChild::error("inner".to_string(), span!(pos!(1, 0, 1), pos!(2, 0, 2)), vec![]),
+                    ]),
+                    leaf!("a"),
+                ])
+            );
+
+            assert_eq!(tree.diagnostics(), vec![
+                Diagnostic::new("outer".to_string(), Some(span!(pos!(0, 0, 0), pos!(2, 0, 2)))),
+                Diagnostic::new("inner".to_string(), Some(span!(pos!(1, 0, 1), pos!(2, 0, 2)))),
+            ]);
+        }
+
+        it "collects syntax errors from parsed tree" {
+            let volt = &mut Volt::new();
+            volt.add_grammar_str("Test", r#"list := (item ","?)*; item := [a-z] / &[0-9] [0-9]+.err("number");"#).unwrap();
+            let tree = volt.parse("a,12,b", &RuleId("Test::list".to_string())).unwrap();
+
+            assert_eq!(tree.diagnostics(), vec![
+                Diagnostic::new("number".to_string(), Some(span!(pos!(2, 0, 2), pos!(4, 0, 4)))),
+            ]);
+        }
+
+        it "converts parser error" {
+            let error = ParserError::UnexpectedInput {
+                position: pos!(1, 0, 1),
+                expected: vec![Expression::String("a".to_string()), Expression::Wildcard],
+            };
+
+            assert_eq!(Diagnostic::from(&error), Diagnostic::new("unexpected input, expected \"a\", _".to_string(), Some(span!(pos!(1, 0, 1), pos!(1, 0, 1)))));
+            assert_eq!(Diagnostic::from(&ParserError::ExceededMaxRecursion { max_recursion: 8 }), Diagnostic::new("exceeded max recursion of 8".to_string(), None));
+        }
+
+        it "renders source line with underline" {
+            let diagnostic = Diagnostic::new("number".to_string(), Some(span!(pos!(6, 1, 2), pos!(8, 1, 4))));
+
+            assert_eq!(diagnostic.render("a,b\n\tc12\n"), [
+                "error: number",
+                " --> 2:3",
+                "  |",
+                "2 | \tc12",
+                "  | \t ^^",
+                "",
+            ].join("\n"));
+        }
+
+        it "renders zero width span with caret" {
+            let diagnostic = Diagnostic::new("unexpected input".to_string(), Some(span!(pos!(3, 0, 3), pos!(3, 0, 3))));
+
+            assert_eq!(diagnostic.render("abc"), [
+                "error: unexpected input",
+                " --> 1:4",
+                "  |",
+                "1 | abc",
+                "  |    ^",
+                "",
+            ].join("\n"));
+        }
+
+        it "renders diagnostic without span" {
+            let diagnostic = Diagnostic::new("exceeded max recursion".to_string(), None);
+            assert_eq!(diagnostic.render("abc"), "error: exceeded max recursion\n");
+        }
+
+        it "renders with ansi colors" {
+            let diagnostic = Diagnostic::new("msg".to_string(), Some(span!(pos!(0, 0, 0), pos!(1, 0, 1))));
+
+            assert_eq!(diagnostic.render_colored("a"), [
+                "\x1b[1;31merror\x1b[0m: \x1b[1mmsg\x1b[0m",
+                " \x1b[1;34m-->\x1b[0m 1:1",
+                "  \x1b[1;34m|\x1b[0m",
+                "\x1b[1;34m1 |\x1b[0m a",
+                "  \x1b[1;34m|\x1b[0m \x1b[1;31m^\x1b[0m",
+                "",
+            ].join("\n"));
+        }
+    }
+}
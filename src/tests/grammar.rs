@@ -0,0 +1,235 @@
+use {
+    // Use volt to resolve items in derive macro.
+    crate as volt,
+    crate::*,
+    crate::grammar::*,
+    crate::parser::*,
+    crate::tree::*,
+    volt_derive::VoltModuleDefinition,
+    speculate::speculate,
+};
+
+speculate!{
+    before {
+        #[allow(unused)]
+        let volt = &mut Volt::new();
+
+        #[allow(unused)]
+        let rule_id = |id: &str| RuleId(id.to_string());
+    }
+
+    describe "grammar loader" {
+        it "registers rules and parses input with them" {
+            let source = r###"
+                // Sum of numbers.
+                expr := number ("+"## number)*;
+                number := [0-9]+.join;
+            "###;
+
+            volt.add_grammar_str("Calc", source).unwrap();
+
+            assert_eq!(volt.parse("1+23", &rule_id("Calc::expr")), Ok(tree!(
+                node!("Calc::expr" => [
+                    node!("Calc::number" => [
+                        leaf!("1"),
+                    ]),
+                    node!("Calc::number" => [
+                        leaf!("23"),
+                    ]),
+                ])
+            )));
+        }
+
+        it "supports prefixes and suffixes" {
+            let source = r###"
+                main := &"a" (!"b" _)#chars "c"? "d"{2} ("e" / "f")### "g".err("msg") "h".catch("msg") "i".catch_to(";", "msg");
+            "###;
+
+            volt.add_grammar_str("Test", source).unwrap();
+
+            assert_eq!(volt.parse("acddeg;", &rule_id("Test::main")), Ok(tree!(
+                node!("Test::main" => [
+                    node!("chars" => [
+                        leaf!("a"),
+                    ]),
+                    leaf!("c"),
+                    leaf!("d"),
+                    leaf!("d"),
+                    leaf!("e"),
+                    error!("msg", [
+                        leaf!("g"),
+                    ]),
+                    error!("msg", []),
+                    error!("msg", [
+                        leaf!(";"),
+                    ]),
+                ])
+            )));
+        }
+
+        it "parses left recursive rules" {
+            volt.add_grammar_str("Calc", r###"expr := expr "-" "1" / "1";"###).unwrap();
+
+            assert_eq!(volt.parse("1-1", &rule_id("Calc::expr")), Ok(tree!(
+                node!("Calc::expr" => [
+                    node!("Calc::expr" => [
+                        leaf!("1"),
+                    ]),
+                    leaf!("-"),
+                    leaf!("1"),
+                ])
+            )));
+        }
+
+        it "resolves rules of other modules" {
+            volt.add_grammar_str("A", r###"a := "a";"###).unwrap();
+            volt.add_grammar_str("B", r###"b := A::a "b";"###).unwrap();
+
+            assert_eq!(volt.parse("ab", &rule_id("B::b")), Ok(tree!(
+                node!("B::b" => [
+                    node!("A::a" => [
+                        leaf!("a"),
+                    ]),
+                    leaf!("b"),
+                ])
+            )));
+        }
+
+        it "keeps display output loadable" {
+            let source = r###"
+                main := (&"a" / !"\"\n")#g "b"* "c"+ "d"? "e"{1,3} "f"{2,} sub## sub### sub.join sub.expand_once <ident> ("i" ~ "f" / "g" / throw("l\"1")) _.capture("c") backref("c") indent() same_indent() dedent() [\[\]].err("x").catch("y").catch_to(_, "z");
+                sub := "s";
+            "###;
+
+            let rules = parse_grammar("Test", source).unwrap();
+            let reloaded_rules = parse_grammar("Test", &rules.to_string()).unwrap();
+
+            assert_eq!(reloaded_rules.to_string(), rules.to_string());
+        }
+
+        it "keeps display output of precedence loadable" {
+            let rules = RuleVec(vec![
+                Rule::new(rule_id("Test::expr"), precedence(str("1"), vec![
+                    PrecedenceLevel::left(vec![str("+"), str("-")]),
+                    PrecedenceLevel::right(vec![str("^")]),
+                    PrecedenceLevel::prefix(vec![choice![str("!"), str("~")]]),
+                    PrecedenceLevel::postfix(vec![seq![str("["), str("]")]]),
+                ])),
+            ]);
+
+            let reloaded_rules = parse_grammar("Test", &rules.to_string()).unwrap();
+            assert_eq!(reloaded_rules.to_string(), rules.to_string());
+
+            volt.add_grammar_str("Calc", r###"expr := "1".precedence(left("+"), prefix("-"));"###).unwrap();
+
+            assert_eq!(volt.parse("-1+1", &rule_id("Calc::expr")), Ok(tree!(
+                node!("Calc::expr" => [
+                    node!("binary" => [
+                        node!("prefix" => [
+                            leaf!("-"),
+                            leaf!("1"),
+                        ]),
+                        leaf!("+"),
+                        leaf!("1"),
+                    ]),
+                ])
+            )));
+        }
+    }
+
+    describe "grammar errors" {
+        it "reports unknown rule reference with position" {
+            assert_eq!(volt.add_grammar_str("Test", "a := \"a\";\nb := a c;"), Err(GrammarError::UnknownRule {
+                id: rule_id("Test::c"),
+                position: pos!(17, 1, 7),
+            }));
+        }
+
+        it "reports syntax error" {
+            assert_eq!(volt.add_grammar_str("Test", "a := ;"), Err(GrammarError::Syntax(ParserError::UnexpectedInput {
+                position: pos!(5, 0, 5),
+                expected: vec![
+                    Expression::CharacterClass(regex::Regex::new("[ \t\r\n]").unwrap()),
+                    Expression::String("//".to_string()),
+                    Expression::Rule(rule_id("GrammarModule::choice")),
+                ],
+            })));
+        }
+
+        it "reports duplicate rule" {
+            volt.add_grammar_str("Test", r###"a := "a";"###).unwrap();
+
+            assert_eq!(volt.add_grammar_str("Test", r###"a := "b";"###), Err(GrammarError::DuplicateRule {
+                id: rule_id("Test::a"),
+            }));
+        }
+
+        it "reports empty string" {
+            assert_eq!(volt.add_grammar_str("Test", r###"a := "";"###), Err(GrammarError::EmptyString {
+                position: Some(pos!(5, 0, 5)),
+            }));
+        }
+
+        it "reports invalid loop range" {
+            assert_eq!(volt.add_grammar_str("Test", r###"a := "a"{2,1};"###), Err(GrammarError::InvalidLoopRange {
+                position: pos!(9, 0, 9),
+            }));
+        }
+    }
+
+    describe "fallible registration" {
+        it "reports duplicate rule of module without registering it" {
+            volt.add_grammar_str("FallibleModule", r###"a := "a";"###).unwrap();
+
+            assert_eq!(volt.try_add_module(FallibleModule::new()), Err(GrammarError::DuplicateRule {
+                id: rule_id("FallibleModule::a"),
+            }));
+
+            assert!(volt.parse("b", &rule_id("FallibleModule::b")).is_err());
+        }
+
+        it "registers module" {
+            assert_eq!(volt.try_add_module(FallibleModule::new()), Ok(()));
+            assert!(volt.parse("b", &rule_id("FallibleModule::b")).is_ok());
+        }
+
+        it "reports empty string" {
+            assert!(try_str("a").is_ok());
+            assert_eq!(try_str("").err(), Some(GrammarError::EmptyString { position: None }));
+        }
+
+        it "reports invalid character class" {
+            assert!(try_chars("a-z").is_ok());
+
+            assert_eq!(try_chars("z-a").err(), Some(GrammarError::InvalidCharacterClass {
+                pattern: "z-a".to_string(),
+                position: None,
+            }));
+        }
+
+        it "implements error trait" {
+            let error: Box<dyn std::error::Error> = Box::new(GrammarError::DuplicateRule { id: rule_id("A::a") });
+            assert_eq!(error.to_string(), "rule ID `A::a` is already declared");
+
+            assert_eq!(
+                GrammarError::UnknownRule { id: rule_id("A::b"), position: pos!(3, 1, 2) }.to_string(),
+                "unknown rule `A::b` at 2:3",
+            );
+        }
+    }
+}
+
+#[derive(VoltModuleDefinition)]
+struct FallibleModule {
+    a: Element,
+    b: Element,
+}
+
+impl VoltModule for FallibleModule {
+    fn new() -> FallibleModule {
+        define_rules!{
+            a := str("a");
+            b := str("b");
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use {
+    // Use volt to resolve items in derive macro.
+    crate as volt,
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    std::sync::atomic::{AtomicUsize, Ordering},
+    proptest::prelude::*,
+    volt_derive::VoltModuleDefinition,
+    speculate::speculate,
+};
+
+fn incremental_volt() -> Volt {
+    let mut volt = Volt::new();
+
+    volt.add_grammar_str("Incremental", r###"
+        file := stmt*;
+        stmt := (name "=" expr ";").catch_to(";", "invalid_stmt");
+        name := [a-zあ-ん]+.join.lexical;
+        expr := expr "+" term / term;
+        term := number / name !"=" / "(" expr ")";
+        number := [0-9]+.join.lexical;
+        spacing := (" " / "\n")*;
+    "###).unwrap();
+
+    volt.set_trivia("Incremental", RuleId("Incremental::spacing".to_string()));
+    volt
+}
+
+static EVALUATED_ITEMS: AtomicUsize = AtomicUsize::new(0);
+
+speculate!{
+    before {
+        #[allow(unused)]
+        let volt = &mut incremental_volt();
+        #[allow(unused)]
+        let entry = RuleId("Incremental::file".to_string());
+    }
+
+    describe "incremental reparsing" {
+        it "applies text edit" {
+            assert_eq!(TextEdit::new(2, 3, "xy").apply("a=1;"), "a=xy;");
+            assert_eq!(TextEdit::new(4, 4, "\n").apply("a=1;"), "a=1;\n");
+        }
+
+        it "generates same tree as full parsing" {
+            let input = "a = 1;\nb = (a + 2);\nc = b;\n";
+            let tree = volt.parse(input, &entry).unwrap();
+
+            for edit in [
+                TextEdit::new(4, 5, "12"),
+                TextEdit::new(7, 8, "bb"),
+                TextEdit::new(0, 0, "x = 3;\n"),
+                TextEdit::new(19, 21, ""),
+                TextEdit::new(11, 11, " + 3"),
+                TextEdit::new(5, 6, ""),
+            ] {
+                let new_input = edit.apply(input);
+                assert_eq!(volt.reparse(&tree, input, &edit, &entry), volt.parse(&new_input, &entry), "{:?}", edit);
+            }
+        }
+
+        it "relocates lines and columns of reused nodes" {
+            let tree = volt.parse("a=1;\nb=2;", &entry).unwrap();
+            let new_tree = volt.reparse(&tree, "a=1;\nb=2;", &TextEdit::new(0, 0, "\n"), &entry).unwrap();
+            assert_eq!(new_tree.root.children.get_node(1).span, span!(pos!(6, 2, 0), pos!(10, 2, 4)));
+        }
+
+        it "reuses unchanged rule nodes" {
+            let volt = &mut Volt::new();
+            volt.add_module(IncrementalModule::new());
+            let entry = RuleId("IncrementalModule::list".to_string());
+
+            let tree = volt.parse("a;b;c;d;", &entry).unwrap();
+            EVALUATED_ITEMS.store(0, Ordering::SeqCst);
+
+            let new_tree = volt.reparse(&tree, "a;b;c;d;", &TextEdit::new(4, 5, "x"), &entry);
+            assert_eq!(EVALUATED_ITEMS.load(Ordering::SeqCst), 1);
+            assert_eq!(new_tree, volt.parse("a;b;x;d;", &entry));
+        }
+
+        it "reuses nodes after multibyte characters" {
+            let input = "ああ=い;b=c;d=e;";
+            let tree = volt.parse(input, &entry).unwrap();
+            let edit = TextEdit::new(input.len() - 2, input.len() - 2, "f+");
+
+            assert_eq!(volt.reparse(&tree, input, &edit, &entry), volt.parse("ああ=い;b=c;d=f+e;", &entry));
+        }
+
+        it "reports invalid text edit" {
+            let input = "あ=1;";
+            let tree = volt.parse(input, &entry).unwrap();
+
+            assert_eq!(volt.reparse(&tree, input, &TextEdit::new(1, 1, "a"), &entry), Err(ParserError::InvalidTextEdit { start: 1, end: 1 }));
+            assert_eq!(volt.reparse(&tree, input, &TextEdit::new(3, 0, "a"), &entry), Err(ParserError::InvalidTextEdit { start: 3, end: 0 }));
+            assert_eq!(volt.reparse(&tree, input, &TextEdit::new(3, 10, "a"), &entry), Err(ParserError::InvalidTextEdit { start: 3, end: 10 }));
+        }
+
+        it "reports errors of full parsing" {
+            let input = "a = 1;";
+            let tree = volt.parse(input, &entry).unwrap();
+            let edit = TextEdit::new(6, 6, "b");
+            assert_eq!(volt.reparse(&tree, input, &edit, &entry), volt.parse("a = 1;b", &entry));
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn reparses_into_same_tree_as_full_parsing(
+        input in "( ?[abあ]{1,2} ?= ?[1aい( +)]{0,5};\n?){0,6}",
+        start in 0..60usize,
+        len in 0..6usize,
+        replacement in "[abあ1 ;=+()\n]{0,4}",
+    ) {
+        let volt = incremental_volt();
+        let entry = RuleId("Incremental::file".to_string());
+
+        // Edits are placed on character boundaries.
+        let boundary = |index: usize| (0..=index.min(input.len())).rev().find(|i| input.is_char_boundary(*i)).unwrap();
+        let start = boundary(start);
+        let edit = TextEdit::new(start, boundary(start + len), &replacement);
+
+        if let Ok(tree) = volt.parse(&input, &entry) {
+            prop_assert_eq!(volt.reparse(&tree, &input, &edit, &entry), volt.parse(&edit.apply(&input), &entry));
+        }
+    }
+}
+
+fn count_item(children: Vec<SyntaxChild>) -> Vec<SyntaxChild> {
+    EVALUATED_ITEMS.fetch_add(1, Ordering::SeqCst);
+    children
+}
+
+#[derive(VoltModuleDefinition)]
+struct IncrementalModule {
+    list: Element,
+    item: Element,
+}
+
+impl VoltModule for IncrementalModule {
+    fn new() -> IncrementalModule {
+        define_rules!{
+            list := IncrementalModule::item().min(0);
+            item := seq![chars("a-z"), str(";")].reduce(count_item);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use {
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    proptest::prelude::*,
+    speculate::speculate,
+};
+
+fn indentation_volt() -> Volt {
+    let mut volt = Volt::new();
+
+    volt.add_grammar_str("Indent", r###"
+        file := stmts;
+        stmts := stmt (same_indent() stmt)*;
+        stmt := "if "## name ":\n"## block / name "\n"##;
+        block := indent() stmts dedent();
+        name := [a-z]+.join;
+        retry := indent() "x" / indent() "y";
+    "###).unwrap();
+
+    volt
+}
+
+speculate!{
+    before {
+        #[allow(unused)]
+        let volt = &mut indentation_volt();
+        #[allow(unused)]
+        let entry = RuleId("Indent::file".to_string());
+        #[allow(unused)]
+        let input = "if a:\n  b\n  if c:\n    d\ne\n";
+    }
+
+    describe "indentation" {
+        it "parses nested blocks" {
+            assert_eq!(
+                volt.parse(input, &entry).unwrap().to_sexp(),
+                "(Indent::file (Indent::stmts (Indent::stmt (Indent::name \"a\") (Indent::block (Indent::stmts (Indent::stmt (Indent::name \"b\")) (Indent::stmt (Indent::name \"c\") (Indent::block (Indent::stmts (Indent::stmt (Indent::name \"d\")))))))) (Indent::stmt (Indent::name \"e\"))))",
+            );
+        }
+
+        it "rejects inconsistent indentation" {
+            assert!(volt.parse("if a:\n    b\n  c\n", &entry).is_err());
+            assert!(volt.parse("if a:\nb\n", &entry).is_err());
+        }
+
+        it "restores indentation stack on backtracking" {
+            assert!(volt.parse("  y", &RuleId("Indent::retry".to_string())).is_ok());
+        }
+
+        it "generates same tree with memoization" {
+            let expected = volt.parse(input, &entry);
+            volt.set_memoization(true);
+            assert_eq!(volt.parse(input, &entry), expected);
+        }
+
+        it "generates same tree with reparsing" {
+            let tree = volt.parse(input, &entry).unwrap();
+
+            for edit in [
+                TextEdit::new(20, 20, "  "),
+                TextEdit::new(8, 8, "  "),
+                TextEdit::new(24, 24, "  "),
+                TextEdit::new(0, 0, "x\n"),
+            ] {
+                assert_eq!(volt.reparse(&tree, input, &edit, &entry), volt.parse(&edit.apply(input), &entry), "{:?}", edit);
+            }
+        }
+
+        it "displays indentation elements" {
+            assert_eq!(seq![indent(), dedent(), same_indent()].to_string(), "(indent() dedent() same_indent())");
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn generates_same_tree_with_memoization_and_reparsing(
+        input in "( {0,4}(if [ab]:|[ab])\n){0,6}",
+        start in 0..50usize,
+        replacement in "[ ab\n]{0,3}",
+    ) {
+        let volt = &mut indentation_volt();
+        let entry = RuleId("Indent::file".to_string());
+
+        let start = start.min(input.len());
+        let edit = TextEdit::new(start, start, &replacement);
+        let expected = volt.parse(&edit.apply(&input), &entry);
+
+        if let Ok(tree) = volt.parse(&input, &entry) {
+            prop_assert_eq!(volt.reparse(&tree, &input, &edit, &entry), expected.clone());
+        }
+
+        volt.set_memoization(true);
+        prop_assert_eq!(volt.parse(&edit.apply(&input), &entry), expected);
+    }
+}
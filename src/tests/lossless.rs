@@ -0,0 +1,142 @@
+use {
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    proptest::prelude::*,
+    speculate::speculate,
+};
+
+fn lossless_volt() -> Volt {
+    let mut volt = Volt::new();
+
+    volt.add_grammar_str("Lossless", r###"
+        file := stmt* rest;
+        stmt := (name "=" value ";").catch_to(";", "invalid_stmt");
+        name := [a-z]+.join.lexical;
+        value := number / string / list / "(" value ")" / "-"## value;
+        number := ([0-9] "_"##?)+.join.lexical;
+        string := ("'"## [a-z]* "'"##).join.lexical;
+        list := ("{" (([0-9]+ / [a-z]+ "!") ","?).catch_to(",", "invalid_item")* "}".catch("unclosed_list")).join.lexical;
+        rest := _*##;
+        spacing := (" " / "\n" / "#" (!"\n" _)*)*;
+    "###).unwrap();
+
+    volt.set_trivia("Lossless", RuleId("Lossless::spacing".to_string()));
+    volt.set_lossless(true);
+    volt
+}
+
+speculate!{
+    before {
+        let volt = &mut lossless_volt();
+        let entry = RuleId("Lossless::file".to_string());
+    }
+
+    describe "lossless mode" {
+        it "attaches hidden input and trivia to adjacent leaves" {
+            let tree = volt.parse("a = -1; # c\n", &entry).unwrap();
+
+            assert_eq!(
+                tree.to_sexp(),
+                r##"(Lossless::file (Lossless::stmt (Lossless::name "a") (:leaf "=" :leading " ") (Lossless::value (Lossless::value (Lossless::number (:leaf "1" :leading " -")))) (:leaf ";" :trailing " # c\n")) (Lossless::rest))"##,
+            );
+
+            assert_eq!(tree.to_source(), "a = -1; # c\n");
+        }
+
+        it "keeps children same as without lossless mode" {
+            let tree = volt.parse("a = -1;", &entry).unwrap();
+            volt.set_lossless(false);
+            let lossy_tree = volt.parse("a = -1;", &entry).unwrap();
+
+            let (stmt, lossy_stmt) = (tree.root.children.get_node(0), lossy_tree.root.children.get_node(0));
+            assert_eq!(stmt.children.len(), lossy_stmt.children.len());
+            assert_eq!(stmt.children.get_leaf(1).value, lossy_stmt.children.get_leaf(1).value);
+        }
+
+        it "keeps hidden input inside joined leaves as source" {
+            let tree = volt.parse("s = 'ab'; n=1_0;", &entry).unwrap();
+            let value_leaf = |index: usize| tree.root.children.get_node(index).children.get_node(2).children.get_node(0).children.get_leaf(0).clone();
+
+            let string = value_leaf(0);
+            assert_eq!(string.value, "ab");
+            assert_eq!(string.source, Some("'ab'".to_string()));
+            assert_eq!(string.leading_trivia, " ");
+
+            let number = value_leaf(1);
+            assert_eq!(number.value, "10");
+            assert_eq!(number.to_source(), "1_0");
+
+            assert_eq!(tree.to_source(), "s = 'ab'; n=1_0;");
+        }
+
+        it "keeps hidden input of nodes without leaves as hidden leaves" {
+            let tree = volt.parse("a=1; x", &entry).unwrap();
+
+            assert_eq!(
+                tree.to_sexp(),
+                r##"(Lossless::file (Lossless::stmt (Lossless::name "a") "=" (Lossless::value (Lossless::number "1")) (:leaf ";" :trailing " ")) (Lossless::rest (:hidden "x")))"##,
+            );
+        }
+
+        it "keeps skipped input in errors" {
+            let tree = volt.parse("a ? ;b=1;", &entry).unwrap();
+            let error = tree.root.children.get_node(0).children.get_error(0);
+
+            let mut leaf = SyntaxLeaf::new(span!(pos!(4, 0, 4), pos!(5, 0, 5)), ";".to_string());
+            leaf.leading_trivia = "a ? ".to_string();
+
+            assert_eq!(error.message, "invalid_stmt");
+            assert_eq!(error.children, vec![SyntaxChild::Leaf(leaf)]);
+            assert_eq!(tree.to_source(), "a ? ;b=1;");
+        }
+
+        it "keeps errors inside joined leaves in input order" {
+            let tree = volt.parse("l={1,a?,2 ;", &entry).unwrap();
+            let value = tree.root.children.get_node(0).children.get_node(2).children.get_node(0);
+
+            assert_eq!(
+                SyntaxChild::Node(value.clone()).to_sexp(),
+                r##"(Lossless::list "{1," (:error "invalid_item" (:leaf "," :leading "a?")) "2" (:error "unclosed_list"))"##,
+            );
+
+            assert_eq!(tree.to_source(), "l={1,a?,2 ;");
+        }
+
+        it "drops hidden input when disabled" {
+            volt.set_lossless(false);
+            assert_eq!(volt.parse("a = -1; x", &entry).unwrap().to_source(), "a=1;");
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn reproduces_input_from_leaves(input in "(a=\\{[1a!?, ]{0,6}\\}?;|[ab1_' \n#();=,!{}-]){0,20}") {
+        let volt = &mut lossless_volt();
+        let entry = RuleId("Lossless::file".to_string());
+
+        let tree = volt.parse(&input, &entry).unwrap();
+        prop_assert_eq!(tree.to_source(), input.clone());
+
+        volt.set_memoization(true);
+        prop_assert_eq!(volt.parse(&input, &entry), Ok(tree));
+    }
+
+    #[test]
+    fn reparses_into_same_tree_as_full_parsing(
+        input in "(a=\\{[1a!?, ]{0,6}\\}?;|[ab1_' \n#();=,!{}-]){0,20}",
+        start in 0..40usize,
+        len in 0..6usize,
+        replacement in "[ab1_' \n;=,!{}-]{0,4}",
+    ) {
+        let volt = lossless_volt();
+        let entry = RuleId("Lossless::file".to_string());
+
+        let start = start.min(input.len());
+        let edit = TextEdit::new(start, (start + len).min(input.len()), &replacement);
+        let tree = volt.parse(&input, &entry).unwrap();
+
+        prop_assert_eq!(volt.reparse(&tree, &input, &edit, &entry), volt.parse(&edit.apply(&input), &entry));
+    }
+}
@@ -0,0 +1,87 @@
+use {
+    crate::*,
+    crate::element::Expression,
+    crate::observer::*,
+    crate::tree::*,
+    speculate::speculate,
+};
+
+struct Recorder(Vec<String>);
+
+impl ParseObserver for Recorder {
+    fn enter_rule(&mut self, id: &RuleId, position: &InputPosition) {
+        self.0.push(format!("enter {} {}", id, position.index));
+    }
+
+    fn succeed_rule(&mut self, id: &RuleId, span: &Span) {
+        self.0.push(format!("succeed {} {}-{}", id, span.start.index, span.end.index));
+    }
+
+    fn fail_rule(&mut self, id: &RuleId, position: &InputPosition) {
+        self.0.push(format!("fail {} {}", id, position.index));
+    }
+
+    fn attempt_expression(&mut self, expr: &Expression, span: &Span, matched: bool) {
+        self.0.push(format!("{} {} {}-{}", expr, matched, span.start.index, span.end.index));
+    }
+}
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+        volt.add_grammar_str("Test", r#"pair := key "=" value; key := "b" / [a-z]; value := [0-9];"#).unwrap();
+        let entry = RuleId("Test::pair".to_string());
+    }
+
+    describe "parse observer" {
+        it "reports rules and expressions" {
+            let mut recorder = Recorder(Vec::new());
+            let result = volt.parse_with_observer("a=1", &entry, &mut recorder);
+
+            assert_eq!(result, volt.parse("a=1", &entry));
+
+            assert_eq!(recorder.0, vec![
+                "enter Test::pair 0",
+                "enter Test::key 0",
+                "\"b\" false 0-0",
+                "[a-z] true 0-1",
+                "succeed Test::key 0-1",
+                "\"=\" true 1-2",
+                "enter Test::value 2",
+                "[0-9] true 2-3",
+                "succeed Test::value 2-3",
+                "succeed Test::pair 0-3",
+            ]);
+        }
+
+        it "reports rule failure" {
+            let mut recorder = Recorder(Vec::new());
+            assert!(volt.parse_with_observer("a=b", &entry, &mut recorder).is_err());
+
+            assert_eq!(&recorder.0[recorder.0.len() - 3..], vec![
+                "[0-9] false 2-2",
+                "fail Test::value 2",
+                "fail Test::pair 0",
+            ]);
+        }
+
+        it "writes indented trace" {
+            let mut trace = TraceObserver::new(Vec::new());
+            volt.parse_with_observer("a=1", &entry, &mut trace).unwrap();
+
+            assert_eq!(String::from_utf8(trace.into_inner()).unwrap(), [
+                "Test::pair at 1:1",
+                "  Test::key at 1:1",
+                "    \"b\" failed at 1:1",
+                "    [a-z] matched 1:1-1:2",
+                "  Test::key matched 1:1-1:2",
+                "  \"=\" matched 1:2-1:3",
+                "  Test::value at 1:3",
+                "    [0-9] matched 1:3-1:4",
+                "  Test::value matched 1:3-1:4",
+                "Test::pair matched 1:1-1:4",
+                "",
+            ].join("\n"));
+        }
+    }
+}
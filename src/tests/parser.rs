@@ -4,10 +4,20 @@ use {
     crate::*,
     crate::parser::*,
     crate::tree::*,
+    crate::observer::ParseObserver,
     volt_derive::VoltModuleDefinition,
     speculate::speculate,
 };
 
+// Counts rule applications including recalled ones.
+struct RuleCounter(usize);
+
+impl ParseObserver for RuleCounter {
+    fn enter_rule(&mut self, _id: &RuleId, _position: &InputPosition) {
+        self.0 += 1;
+    }
+}
+
 speculate!{
     before {
         let volt = &mut Volt::new();
@@ -23,19 +33,30 @@ speculate!{
         #[allow(unused)]
         let expect_failure = |input: &str, rule_id: &str, expected: ParserError|
             assert_ast(input, rule_id, Err(expected));
+
+        #[allow(unused)]
+        let unexpected_input = |index: usize, expected: Vec<Element>| ParserError::UnexpectedInput {
+            position: pos!(index, 0, index),
+            expected: expected.into_iter().map(|elem| match elem {
+                Element::Expression(expr) => expr,
+                _ => unreachable!(),
+            }).collect(),
+        };
     }
 
     describe "input index" {
         it "generates line start indexes" {
             assert_eq!(InputPositionCounter::from(""), (
                 InputPositionCounter {
-                    lines: vec![(0, 0)],
+                    lines: vec![0],
+                    multibyte_chars: vec![],
                 }
             ));
 
             assert_eq!(InputPositionCounter::from("a\nあ\n"), (
                 InputPositionCounter {
-                    lines: vec![(0, 2), (2, 2), (4, 0)],
+                    lines: vec![0, 2, 6],
+                    multibyte_chars: vec![(5, 2)],
                 }
             ));
         }
@@ -52,17 +73,223 @@ speculate!{
         }
     }
 
+    describe "parser error" {
+        it "reports configured max recursion" {
+            let volt = &mut Volt::new();
+            volt.add_module(TestModule::new());
+            volt.set_max_recursion(2);
+
+            assert_eq!(
+                volt.parse("((a))", &RuleId("TestModule::pathological_expr".to_string())),
+                Err(ParserError::ExceededMaxRecursion { max_recursion: 2 }),
+            );
+        }
+
+        it "displays messages" {
+            assert_eq!(unexpected_input(1, vec![str("a"), wildcard()]).to_string(), "unexpected input at 1:2, expected \"a\", _");
+            assert_eq!(unexpected_input(1, vec![]).to_string(), "unexpected input at 1:2");
+            assert_eq!(ParserError::RuleNotExists { id: RuleId("A::a".to_string()) }.to_string(), "rule `A::a` does not exist");
+            assert_eq!(ParserError::ExceededMaxRecursion { max_recursion: 8 }.to_string(), "exceeded max recursion of 8");
+        }
+
+        it "implements error trait" {
+            let error: Box<dyn std::error::Error> = Box::new(ParserError::ExceededMaxRecursion { max_recursion: 8 });
+            assert_eq!(error.to_string(), "exceeded max recursion of 8");
+        }
+    }
+
+    describe "unexpected input" {
+        it "reports furthest failure position with line and column" {
+            expect_failure("a\nb\n", "TestModule::input_index", ParserError::UnexpectedInput {
+                position: pos!(2, 1, 0),
+                expected: vec![Expression::String("a".to_string())],
+            });
+        }
+
+        it "reports rule name when rule fails at its start" {
+            expect_failure("(", "TestModule::pathological_expr", unexpected_input(1, vec![TestModule::pathological_expr()]));
+        }
+
+        it "reports end of input at last line" {
+            expect_failure("a\n", "TestModule::input_index", ParserError::UnexpectedInput {
+                position: pos!(2, 1, 0),
+                expected: vec![Expression::String("a".to_string())],
+            });
+        }
+    }
+
+    describe "memoization" {
+        it "parses pathological grammar in linear time" {
+            let memoized_volt = &mut Volt::new();
+            memoized_volt.set_memoization(true);
+            memoized_volt.add_module(TestModule::new());
+
+            let count_rules = |depth: usize| {
+                let input = format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+                let mut counter = RuleCounter(0);
+                assert!(memoized_volt.parse_with_observer(&input, &RuleId("TestModule::pathological_expr".to_string()), &mut counter).is_ok());
+                counter.0
+            };
+
+            // Rule applications grow by the same number for each nesting level.
+            let (count5, count10, count20) = (count_rules(5), count_rules(10), count_rules(20));
+            assert_eq!(count20 - count10, (count10 - count5) * 2);
+        }
+
+        it "generates same tree as parsing without memoization" {
+            let memoized_volt = &mut Volt::new();
+            memoized_volt.set_memoization(true);
+            memoized_volt.add_module(TestModule::new());
+
+            let rule_id = RuleId("TestModule::pathological_expr".to_string());
+
+            for input in ["a", "a+a", "(a-a)+a", "((a))-(a+a)"] {
+                assert_eq!(memoized_volt.parse(input, &rule_id), volt.parse(input, &rule_id));
+            }
+        }
+
+        it "reports same error as parsing without memoization" {
+            let grammar = r#"
+                a := !b "x" / b;
+                b := "p" "q";
+            "#;
+
+            let errors = [false, true].map(|memoization| {
+                let volt = &mut Volt::new();
+                volt.set_memoization(memoization);
+                volt.add_grammar_str("M", grammar).unwrap();
+                volt.parse("pz", &RuleId("M::a".to_string())).unwrap_err()
+            });
+
+            assert_eq!(errors[1], errors[0]);
+            assert_eq!(errors[0].to_string(), "unexpected input at 1:2, expected \"q\"");
+        }
+    }
+
+    describe "left recursion" {
+        it "parses direct left recursion into left-associative nodes" {
+            expect_success("1-1-1", "TestModule::direct_left_recursion", tree!(
+                node!("TestModule::direct_left_recursion" => [
+                    node!("TestModule::direct_left_recursion" => [
+                        node!("TestModule::direct_left_recursion" => [
+                            leaf!("1"),
+                        ]),
+                        leaf!("-"),
+                        leaf!("1"),
+                    ]),
+                    leaf!("-"),
+                    leaf!("1"),
+                ])
+            ));
+        }
+
+        it "parses indirect left recursion" {
+            expect_success("a.a", "TestModule::indirect_left_recursion", tree!(
+                node!("TestModule::indirect_left_recursion" => [
+                    node!("TestModule::indirect_left_recursion_operand" => [
+                        node!("TestModule::indirect_left_recursion" => [
+                            leaf!("a"),
+                        ]),
+                    ]),
+                    leaf!("."),
+                    leaf!("a"),
+                ])
+            ));
+        }
+
+        it "parses left recursion hidden behind nullable element" {
+            expect_success("1+1", "TestModule::hidden_left_recursion", tree!(
+                node!("TestModule::hidden_left_recursion" => [
+                    node!("TestModule::hidden_left_recursion" => [
+                        leaf!("1"),
+                    ]),
+                    leaf!("+"),
+                    leaf!("1"),
+                ])
+            ));
+        }
+
+        it "fails when left recursion has no seed" {
+            expect_failure("1", "TestModule::seedless_left_recursion", unexpected_input(0, vec![TestModule::seedless_left_recursion()]));
+        }
+
+        it "generates same tree with memoization" {
+            let memoized_volt = &mut Volt::new();
+            memoized_volt.set_memoization(true);
+            memoized_volt.add_module(TestModule::new());
+
+            for (input, rule_id) in [("1-1-1", "TestModule::direct_left_recursion"), ("a.a.a", "TestModule::indirect_left_recursion")] {
+                let rule_id = RuleId(rule_id.to_string());
+                assert_eq!(memoized_volt.parse(input, &rule_id), volt.parse(input, &rule_id));
+            }
+        }
+    }
+
+    describe "span" {
+        it "sets spans of nodes and leaves" {
+            let tree = volt.parse("a\na\n", &RuleId("TestModule::input_index".to_string())).unwrap();
+
+            assert_eq!(tree.root.span, span!(pos!(0, 0, 0), pos!(4, 2, 0)));
+            assert_eq!(tree.root.children.get_leaf(1).span, span!(pos!(1, 0, 1), pos!(2, 1, 0)));
+        }
+
+        it "sets spans of groups and joined leaves" {
+            let tree = volt.parse("aa", &RuleId("TestModule::sequence_group".to_string())).unwrap();
+            assert_eq!(tree.root.children.get_node(0).span, span!(pos!(0, 0, 0), pos!(2, 0, 2)));
+
+            let tree = volt.parse("aa", &RuleId("TestModule::join".to_string())).unwrap();
+            assert_eq!(tree.root.children.get_leaf(0).span, span!(pos!(0, 0, 0), pos!(2, 0, 2)));
+        }
+
+        it "sets spans of errors" {
+            let tree = volt.parse("b", &RuleId("TestModule::catch".to_string())).unwrap();
+            assert_eq!(tree.root.children.get_error(0).span, span!(pos!(0, 0, 0), pos!(0, 0, 0)));
+
+            let tree = volt.parse("bb;", &RuleId("TestModule::catch_to".to_string())).unwrap();
+            assert_eq!(tree.root.children.get_error(0).span, span!(pos!(0, 0, 0), pos!(3, 0, 3)));
+        }
+    }
+
+    describe "byte offset" {
+        it "converts byte offsets into character positions" {
+            let counter = InputPositionCounter::from("aあ\nいb");
+
+            assert_eq!(counter.get_position(1), pos!(1, 0, 1));
+            assert_eq!(counter.get_position(4), pos!(2, 0, 2));
+            assert_eq!(counter.get_position(5), pos!(3, 1, 0));
+            assert_eq!(counter.get_position(8), pos!(4, 1, 1));
+            assert_eq!(counter.get_position(9), pos!(5, 1, 2));
+        }
+
+        it "gets input position of multibyte characters" {
+            expect_success("あ\nい", "TestModule::loop_range2", tree!(
+                node!("TestModule::loop_range2" => [
+                    leaf!(pos!(0, 0, 0), "あ"),
+                    leaf!(pos!(1, 0, 1), "\n"),
+                    leaf!(pos!(2, 1, 0), "い"),
+                ])
+            ));
+        }
+
+        it "parses large input in linear time" {
+            let input = "あ".repeat(100000);
+            let result = volt.parse(&input, &RuleId("TestModule::loop_range2".to_string()));
+
+            assert_eq!(result.map(|tree| tree.root.children.len()), Ok(100000));
+        }
+    }
+
     // it "detect max recursion excess" {
-    //     expect_failure("", "TestModule::left_recursion", ParserError::ExceededMaxRecursion);
+    //     expect_failure("", "TestModule::left_recursion", ParserError::ExceededMaxRecursion { max_recursion: 1024 });
     // }
 
     describe "choice element" {
         it "choice consumes characters as much its children 1" {
-            expect_failure("", "TestModule::choice", ParserError::NoMatchedRule);
+            expect_failure("", "TestModule::choice", unexpected_input(0, vec![str("a"), str("b")]));
         }
 
         it "choice consumes characters as much its children 2" {
-            expect_failure("ab", "TestModule::choice", ParserError::NoMatchedRule);
+            expect_failure("ab", "TestModule::choice", unexpected_input(1, vec![]));
         }
 
         it "choices first choice when match" {
@@ -82,17 +309,17 @@ speculate!{
         }
 
         it "choice doesn't match element not exist in children" {
-            expect_failure("c", "TestModule::choice", ParserError::NoMatchedRule);
+            expect_failure("c", "TestModule::choice", unexpected_input(0, vec![str("a"), str("b")]));
         }
     }
 
     describe "sequence element" {
         it "sequence consumes characters as much its children 1" {
-            expect_failure("a", "TestModule::sequence", ParserError::NoMatchedRule);
+            expect_failure("a", "TestModule::sequence", unexpected_input(1, vec![str("b")]));
         }
 
         it "sequence consumes characters as much its children 2" {
-            expect_failure("abc", "TestModule::sequence", ParserError::NoMatchedRule);
+            expect_failure("abc", "TestModule::sequence", unexpected_input(2, vec![]));
         }
 
         it "sequence matches completely same input 1" {
@@ -105,14 +332,14 @@ speculate!{
         }
 
         it "sequence matches completely same input 2" {
-            expect_failure("ac", "TestModule::sequence", ParserError::NoMatchedRule);
+            expect_failure("ac", "TestModule::sequence", unexpected_input(1, vec![str("b")]));
         }
     }
 
     describe "loop element" {
         describe "n times" {
             it "repeats for the number of times in the specified range 1-1" {
-                expect_failure("a", "TestModule::loop_range1", ParserError::NoMatchedRule);
+                expect_failure("a", "TestModule::loop_range1", unexpected_input(1, vec![wildcard()]));
             }
 
             it "repeats for the number of times in the specified range 1-2" {
@@ -125,13 +352,13 @@ speculate!{
             }
 
             it "repeats for the number of times in the specified range 1-3" {
-                expect_failure("aaa", "TestModule::loop_range1", ParserError::NoMatchedRule);
+                expect_failure("aaa", "TestModule::loop_range1", unexpected_input(2, vec![]));
             }
         }
 
         describe "min" {
             it "repeats for the number of times in the specified range 2-1" {
-                expect_failure("", "TestModule::loop_range2", ParserError::NoMatchedRule);
+                expect_failure("", "TestModule::loop_range2", unexpected_input(0, vec![wildcard()]));
             }
 
             it "repeats for the number of times in the specified range 2-2" {
@@ -168,7 +395,7 @@ speculate!{
             }
 
             it "repeats for the number of times in the specified range 3-3" {
-                expect_failure("aa", "TestModule::loop_range3", ParserError::NoMatchedRule);
+                expect_failure("aa", "TestModule::loop_range3", unexpected_input(1, vec![]));
             }
         }
     }
@@ -183,13 +410,13 @@ speculate!{
         }
 
         it "doesn't change input index 2" {
-            expect_failure("b", "TestModule::poslook", ParserError::NoMatchedRule);
+            expect_failure("b", "TestModule::poslook", unexpected_input(0, vec![str("a")]));
         }
     }
 
     describe "negative lookahead element" {
         it "doesn't change input index 1" {
-            expect_failure("a", "TestModule::neglook", ParserError::NoMatchedRule);
+            expect_failure("a", "TestModule::neglook", unexpected_input(0, vec![]));
         }
 
         it "doesn't change input index 2" {
@@ -260,7 +487,7 @@ speculate!{
         }
 
         it "try parsing until end of input" {
-            expect_failure("b", "TestModule::catch_to", ParserError::NoMatchedRule);
+            expect_failure("b", "TestModule::catch_to", unexpected_input(1, vec![str(";")]));
         }
     }
 
@@ -274,7 +501,7 @@ speculate!{
         }
 
         it "doesn't modify the result when input didn't match syntax rule" {
-            expect_failure("b", "TestModule::tree_reduction", ParserError::NoMatchedRule);
+            expect_failure("b", "TestModule::tree_reduction", unexpected_input(0, vec![str("a")]));
         }
     }
 
@@ -357,13 +584,161 @@ speculate!{
         }
     }
 
+    describe "precedence element" {
+        it "binds tighter levels first" {
+            expect_success("1+2*3", "TestModule::precedence", tree!(
+                node!("TestModule::precedence" => [
+                    node!("binary" => [
+                        leaf!("1"),
+                        leaf!("+"),
+                        node!("binary" => [
+                            leaf!("2"),
+                            leaf!("*"),
+                            leaf!("3"),
+                        ]),
+                    ]),
+                ])
+            ));
+        }
+
+        it "associates binary operators" {
+            expect_success("1-2-3", "TestModule::precedence", tree!(
+                node!("TestModule::precedence" => [
+                    node!("binary" => [
+                        node!("binary" => [
+                            leaf!("1"),
+                            leaf!("-"),
+                            leaf!("2"),
+                        ]),
+                        leaf!("-"),
+                        leaf!("3"),
+                    ]),
+                ])
+            ));
+
+            expect_success("1^2^3", "TestModule::precedence", tree!(
+                node!("TestModule::precedence" => [
+                    node!("binary" => [
+                        leaf!("1"),
+                        leaf!("^"),
+                        node!("binary" => [
+                            leaf!("2"),
+                            leaf!("^"),
+                            leaf!("3"),
+                        ]),
+                    ]),
+                ])
+            ));
+        }
+
+        it "applies prefix and postfix operators" {
+            expect_success("-1*2", "TestModule::precedence", tree!(
+                node!("TestModule::precedence" => [
+                    node!("binary" => [
+                        node!("prefix" => [
+                            leaf!("-"),
+                            leaf!("1"),
+                        ]),
+                        leaf!("*"),
+                        leaf!("2"),
+                    ]),
+                ])
+            ));
+
+            expect_success("--1^2!", "TestModule::precedence", tree!(
+                node!("TestModule::precedence" => [
+                    node!("prefix" => [
+                        leaf!("-"),
+                        node!("prefix" => [
+                            leaf!("-"),
+                            node!("binary" => [
+                                leaf!("1"),
+                                leaf!("^"),
+                                node!("postfix" => [
+                                    leaf!("2"),
+                                    leaf!("!"),
+                                ]),
+                            ]),
+                        ]),
+                    ]),
+                ])
+            ));
+        }
+
+        it "fails when operand is missing" {
+            expect_failure("1+", "TestModule::precedence", unexpected_input(2, vec![str("-"), chars("0-9")]));
+        }
+
+        it "displays levels" {
+            assert_eq!(
+                precedence(chars("0-9"), vec![PrecedenceLevel::left(vec![str("+"), str("-")]), PrecedenceLevel::prefix(vec![str("-")])]).to_string(),
+                "[0-9].precedence(left(\"+\" / \"-\"), prefix(\"-\"))",
+            );
+        }
+    }
+
+    describe "cut element" {
+        it "doesn't try alternatives after cut" {
+            expect_success("abc", "TestModule::cut", tree!(
+                node!("TestModule::cut" => [
+                    leaf!("abc"),
+                ])
+            ));
+
+            expect_failure("ifx", "TestModule::cut", unexpected_input(2, vec![str("(")]));
+        }
+
+        it "fails loop and commits choice when iteration fails after cut" {
+            expect_success("abc", "TestModule::cut_in_loop", tree!(
+                node!("TestModule::cut_in_loop" => [
+                    leaf!("a"),
+                    leaf!("b"),
+                    leaf!("c"),
+                ])
+            ));
+
+            expect_failure("aa", "TestModule::cut_in_loop", unexpected_input(1, vec![str("b")]));
+        }
+
+        it "ends loop when iteration fails before cut" {
+            expect_success("c", "TestModule::cut_in_loop", tree!(
+                node!("TestModule::cut_in_loop" => [
+                    leaf!("c"),
+                ])
+            ));
+        }
+
+        it "is scoped in rules" {
+            expect_success("if)", "TestModule::cut_in_rule", tree!(
+                node!("TestModule::cut_in_rule" => [
+                    leaf!("if)"),
+                ])
+            ));
+        }
+
+        it "discards memo entries before cut" {
+            let memoized_volt = &mut Volt::new();
+            memoized_volt.set_memoization(true);
+            memoized_volt.add_module(TestModule::new());
+
+            let count_entries = |stmt: &str, len: usize| Parser::count_memo_entries(memoized_volt, &stmt.repeat(len), &RuleId("TestModule::cut_stmts".to_string()));
+
+            assert_eq!(count_entries("ifa;", 10), count_entries("ifa;", 100));
+            assert!(count_entries("a;", 10) < count_entries("a;", 100));
+        }
+
+        it "displays cut" {
+            assert_eq!(seq![str("a"), cut()].to_string(), "(\"a\" ~)");
+        }
+    }
+
     describe "around element" {
         it "should have one item" {
-            expect_failure("", "TestModule::around", ParserError::NoMatchedRule);
+            expect_failure("", "TestModule::around", unexpected_input(0, vec![str("'")]));
         }
 
         it "should have enclosure at both side" {
-            expect_failure("a", "TestModule::around", ParserError::NoMatchedRule);
+            expect_failure("a", "TestModule::around", unexpected_input(0, vec![str("'")]));
 
             expect_success("'a'", "TestModule::around", tree!(
                 node!("TestModule::around" => [
@@ -377,7 +752,7 @@ speculate!{
 
     describe "separated element" {
         it "should have at least one item" {
-            expect_failure("", "TestModule::separated", ParserError::NoMatchedRule);
+            expect_failure("", "TestModule::separated", unexpected_input(0, vec![wildcard()]));
         }
 
         it "can put single item" {
@@ -445,11 +820,11 @@ speculate!{
 
     describe "string expression" {
         it "string consumes characters as much its length 1" {
-            expect_failure("a", "TestModule::string", ParserError::NoMatchedRule);
+            expect_failure("a", "TestModule::string", unexpected_input(0, vec![str("ab")]));
         }
 
         it "string consumes characters as much its length 2" {
-            expect_failure("abc", "TestModule::string", ParserError::NoMatchedRule);
+            expect_failure("abc", "TestModule::string", unexpected_input(2, vec![]));
         }
 
         it "string generates single leaf" {
@@ -487,11 +862,11 @@ speculate!{
         }
 
         it "matches a specified character 3" {
-            expect_failure("c", "TestModule::character_class1", ParserError::NoMatchedRule);
+            expect_failure("c", "TestModule::character_class1", unexpected_input(0, vec![chars("ab")]));
         }
 
         it "consumes only one character" {
-            expect_failure("aa", "TestModule::character_class1", ParserError::NoMatchedRule);
+            expect_failure("aa", "TestModule::character_class1", unexpected_input(1, vec![]));
         }
 
         it "supports number specification" {
@@ -513,11 +888,11 @@ speculate!{
 
     describe "wildcard expression" {
         it "wildcard consumes single character 1" {
-            expect_failure("", "TestModule::wildcard", ParserError::NoMatchedRule);
+            expect_failure("", "TestModule::wildcard", unexpected_input(0, vec![wildcard()]));
         }
 
         it "wildcard consumes single character 2" {
-            expect_failure("aa", "TestModule::wildcard", ParserError::NoMatchedRule);
+            expect_failure("aa", "TestModule::wildcard", unexpected_input(1, vec![]));
         }
 
         it "wildcard generates single leaf" {
@@ -560,6 +935,13 @@ struct TestModule {
     join: Element,
     errors_in_join: Element,
     hidden: Element,
+    precedence: Element,
+    cut: Element,
+    cut_in_loop: Element,
+    cut_in_rule: Element,
+    cut_stmts: Element,
+    cut_stmt: Element,
+    cut_stmt_name: Element,
     around: Element,
     separated: Element,
     separated_with_hidden_separator: Element,
@@ -570,6 +952,13 @@ struct TestModule {
     character_class2: Element,
     character_class3: Element,
     wildcard: Element,
+    pathological_expr: Element,
+    pathological_atom: Element,
+    direct_left_recursion: Element,
+    indirect_left_recursion: Element,
+    indirect_left_recursion_operand: Element,
+    hidden_left_recursion: Element,
+    seedless_left_recursion: Element,
 }
 
 impl VoltModule for TestModule {
@@ -587,7 +976,7 @@ impl VoltModule for TestModule {
             error := str("a").err("msg");
             catch := seq![str("a").catch("msg"), wildcard()];
             catch_to := seq![str("a"), str(";")].catch_to("msg", str(";"));
-            tree_reduction := str("a").reduce(|v| vec![SyntaxChild::leaf(v.get_start_position().unwrap(), "reduced".to_string())]);
+            tree_reduction := str("a").reduce(|v| vec![SyntaxChild::leaf(v.get_span().unwrap(), "reduced".to_string())]);
             sequence_group := seq![wildcard(), wildcard()].group("group");
             expression_group := wildcard().group("group");
             expansion := seq![wildcard(), seq![wildcard(), seq![wildcard()].group("group_b")].group("group_a").expand()];
@@ -595,6 +984,19 @@ impl VoltModule for TestModule {
             join := seq![wildcard(), seq![wildcard()].group("g")].join();
             errors_in_join := seq![wildcard(), wildcard().err("e1"), seq![wildcard().err("e2")].group("g")].join();
             hidden := wildcard().hide();
+            precedence := precedence(chars("0-9"), vec![
+                PrecedenceLevel::left(vec![str("+"), str("-")]),
+                PrecedenceLevel::left(vec![str("*")]),
+                PrecedenceLevel::prefix(vec![str("-")]),
+                PrecedenceLevel::right(vec![str("^")]),
+                PrecedenceLevel::postfix(vec![str("!")]),
+            ]);
+            cut := choice![seq![str("if"), cut(), str("("), str(")")], chars("a-z").min(1).join()];
+            cut_in_loop := choice![seq![seq![str("a"), cut(), str("b")].min(0), str("c")], str("aa")];
+            cut_in_rule := choice![TestModule::cut(), wildcard().min(1).join()];
+            cut_stmts := TestModule::cut_stmt().min(0);
+            cut_stmt := choice![seq![str("if"), cut(), TestModule::cut_stmt_name(), str(";")], seq![TestModule::cut_stmt_name(), str(";")]];
+            cut_stmt_name := chars("a-z").min(1).join();
             around := wildcard().around(str("'"));
             separated := wildcard().separate(str(","));
             separated_with_hidden_separator := wildcard().separate(str(",").hide());
@@ -605,6 +1007,17 @@ impl VoltModule for TestModule {
             character_class2 := chars(r"\d");
             character_class3 := chars("[");
             wildcard := wildcard();
+            pathological_expr := choice![
+                seq![TestModule::pathological_atom(), str("+"), TestModule::pathological_expr()],
+                seq![TestModule::pathological_atom(), str("-"), TestModule::pathological_expr()],
+                TestModule::pathological_atom(),
+            ];
+            pathological_atom := choice![seq![str("("), TestModule::pathological_expr(), str(")")], str("a")];
+            direct_left_recursion := choice![seq![TestModule::direct_left_recursion(), str("-"), str("1")], str("1")];
+            indirect_left_recursion := choice![seq![TestModule::indirect_left_recursion_operand(), str("."), str("a")], str("a")];
+            indirect_left_recursion_operand := TestModule::indirect_left_recursion();
+            hidden_left_recursion := choice![seq![str("-").optional(), TestModule::hidden_left_recursion(), str("+"), str("1")], str("1")];
+            seedless_left_recursion := seq![TestModule::seedless_left_recursion(), str("1")];
         }
     }
 }
@@ -0,0 +1,64 @@
+use {
+    // Use volt to resolve items in derive macro.
+    crate as volt,
+    crate::*,
+    crate::tree::*,
+    volt_derive::VoltModuleDefinition,
+    speculate::speculate,
+};
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+        volt.add_module(PredicateModule::new());
+        #[allow(unused)]
+        let parse = |input: &str, rule_name: &str| volt.parse(input, &RuleId(format!("PredicateModule::{}", rule_name)));
+    }
+
+    describe "predicate element" {
+        it "rejects matched text" {
+            assert_eq!(parse("abc", "ident").unwrap().to_sexp(), r#"(PredicateModule::ident "abc")"#);
+            assert!(parse("if", "ident").is_err());
+        }
+
+        it "rejects generated children" {
+            assert!(parse("255", "byte").is_ok());
+            assert!(parse("256", "byte").is_err());
+        }
+
+        it "restores input position on failure" {
+            assert_eq!(parse("if", "optional_ident").unwrap().to_sexp(), r#"(PredicateModule::optional_ident "if")"#);
+        }
+
+        it "reduces children with capturing closure" {
+            assert_eq!(parse("abc", "reduced").unwrap().to_sexp(), r#"(PredicateModule::reduced "abc!")"#);
+        }
+
+        it "displays predicate" {
+            assert_eq!(str("a").check_text(|_| true).to_string(), "\"a\".check");
+        }
+    }
+}
+
+#[derive(VoltModuleDefinition)]
+struct PredicateModule {
+    ident: Element,
+    byte: Element,
+    optional_ident: Element,
+    reduced: Element,
+}
+
+impl VoltModule for PredicateModule {
+    fn new() -> PredicateModule {
+        let reserved = ["if", "else"];
+        let is_reserved = move |s: &str| reserved.contains(&s);
+        let suffix = "!".to_string();
+
+        define_rules!{
+            ident := chars("a-z").min(1).join().check_text(move |s| !is_reserved(s));
+            byte := chars("0-9").min(1).join().check(|children| children.get_leaf(0).value.parse::<u8>().is_ok());
+            optional_ident := seq![chars("a-z").min(1).join().check_text(|s| s != "if").optional(), str("if")];
+            reduced := chars("a-z").min(1).join().reduce(move |children| vec![SyntaxChild::leaf(children.get_span().unwrap(), format!("{}{}", children.join_into_string(), suffix))]);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use {
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    speculate::speculate,
+};
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+
+        volt.add_grammar_str("Recovery", r#"
+            file := stmt*;
+            stmt := name "=" value (";" / throw("missing_semicolon"));
+            name := [a-z]+.join.lexical;
+            value := [0-9]+.join.lexical / throw("missing_value");
+            spacing := (" " / "\n")*;
+        "#).unwrap();
+
+        volt.set_trivia("Recovery", RuleId("Recovery::spacing".to_string()));
+        #[allow(unused)]
+        let entry = RuleId("Recovery::file".to_string());
+    }
+
+    describe "labeled failure" {
+        it "reports multiple recovered labels as errors" {
+            volt.set_recovery("missing_semicolon", str(";").neglook());
+            volt.set_recovery("missing_value", seq![str(";").neglook(), wildcard()].min(0).join().lexical());
+
+            let tree = volt.parse("a = 1\nb = ?;\nc = 3;", &entry).unwrap();
+
+            assert_eq!(
+                tree.to_sexp(),
+                r#"(Recovery::file (Recovery::stmt (Recovery::name "a") "=" (Recovery::value "1") (:error "missing_semicolon")) (Recovery::stmt (Recovery::name "b") "=" (Recovery::value (:error "missing_value" "?")) ";") (Recovery::stmt (Recovery::name "c") "=" (Recovery::value "3") ";"))"#,
+            );
+        }
+
+        it "reports labels without recovery as errors without skipping input" {
+            let tree = volt.parse("a = 1\nb = ;\nc = 3;", &entry).unwrap();
+
+            assert_eq!(
+                tree.to_sexp(),
+                r#"(Recovery::file (Recovery::stmt (Recovery::name "a") "=" (Recovery::value "1") (:error "missing_semicolon")) (Recovery::stmt (Recovery::name "b") "=" (Recovery::value (:error "missing_value")) ";") (Recovery::stmt (Recovery::name "c") "=" (Recovery::value "3") ";"))"#,
+            );
+        }
+
+        it "fails as ordinary failure when recovery doesn't match" {
+            volt.set_recovery("missing_value", str("0"));
+            volt.add_grammar_str("Outer", r#"stmt := Recovery::stmt / "a = ;";"#).unwrap();
+
+            assert_eq!(volt.parse("a = ;", &RuleId("Outer::stmt".to_string())).unwrap().to_sexp(), r#"(Outer::stmt "a = ;")"#);
+
+            assert_eq!(volt.parse("a = ;", &entry), Err(ParserError::UnexpectedInput {
+                position: pos!(4, 0, 4),
+                expected: vec![Expression::Rule(RuleId("Recovery::value".to_string()))],
+            }));
+        }
+
+        it "treats rules of recovery elements as reachable" {
+            volt.add_grammar_str("Skip", "line := (!\"\\n\" _)*;").unwrap();
+            volt.set_recovery("missing_semicolon", Element::Expression(Expression::Rule(RuleId("Skip::line".to_string()))));
+            assert_eq!(volt.validate(&entry), vec![]);
+        }
+
+        it "displays throw" {
+            assert_eq!(throw("missing_value").to_string(), "throw(\"missing_value\")");
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use {
+    crate::*,
+    crate::tree::*,
+    serde_json::json,
+    speculate::speculate,
+};
+
+speculate!{
+    describe "serde" {
+        it "serializes syntax tree into json" {
+            let tree = SyntaxTree::new(SyntaxNode::new("root".to_string(), span!(pos!(0, 0, 0), pos!(1, 0, 1)), vec![
+                SyntaxChild::leaf(span!(pos!(0, 0, 0), pos!(1, 0, 1)), "a".to_string()),
+                SyntaxChild::error("msg".to_string(), span!(pos!(1, 0, 1), pos!(1, 0, 1)), vec![]),
+            ]));
+
+            assert_eq!(serde_json::to_value(&tree).unwrap(), json!({
+                "root": {
+                    "name": "root",
+                    "span": {
+                        "start": { "index": 0, "line": 0, "column": 0 },
+                        "end": { "index": 1, "line": 0, "column": 1 },
+                    },
+                    "children": [
+                        {
+                            "type": "leaf",
+                            "span": {
+                                "start": { "index": 0, "line": 0, "column": 0 },
+                                "end": { "index": 1, "line": 0, "column": 1 },
+                            },
+                            "value": "a",
+                        },
+                        {
+                            "type": "error",
+                            "message": "msg",
+                            "span": {
+                                "start": { "index": 1, "line": 0, "column": 1 },
+                                "end": { "index": 1, "line": 0, "column": 1 },
+                            },
+                            "children": [],
+                        },
+                    ],
+                },
+            }));
+        }
+
+        it "deserializes serialized syntax tree" {
+            let volt = &mut Volt::new();
+            volt.add_grammar_str("Test", r###"list := item (("," / "\n") item)*; item := [a-z]+.join / "?".err("unknown");"###).unwrap();
+
+            let tree = volt.parse("ab,c\n?,d", &RuleId("Test::list".to_string())).unwrap();
+            let json = serde_json::to_string(&tree).unwrap();
+
+            assert_eq!(serde_json::from_str::<SyntaxTree>(&json).unwrap(), tree);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use {
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    speculate::speculate,
+};
+
+// Lexes words, numbers, symbols and spaces one per token on a single line.
+fn lex(input: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+
+    for (i, ch) in input.chars().enumerate() {
+        let kind = match ch {
+            'a'..='z' => "word",
+            '0'..='9' => "number",
+            ' ' => "space",
+            _ => "symbol",
+        };
+
+        match tokens.last_mut() {
+            Some(last) if last.kind == kind && kind != "symbol" => {
+                last.text.push(ch);
+                last.span.end = pos!(i + 1, 0, i + 1);
+            },
+            _ => tokens.push(Token::new(kind, &ch.to_string(), span!(pos!(i, 0, i), pos!(i + 1, 0, i + 1)))),
+        }
+    }
+
+    tokens
+}
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+
+        volt.add_grammar_str("Token", r#"
+            stmt := "let" <word> "=" value ";";
+            value := <number> / [a-z] / "(" value ")";
+            spacing := <space>*;
+        "#).unwrap();
+
+        volt.set_trivia("Token", RuleId("Token::spacing".to_string()));
+        #[allow(unused)]
+        let entry = RuleId("Token::stmt".to_string());
+    }
+
+    describe "token input" {
+        it "matches tokens by kind and text" {
+            let tree = volt.parse_tokens(&lex("let ab = (12);"), &entry).unwrap();
+            assert_eq!(tree.to_sexp(), r#"(Token::stmt "let" "ab" "=" (Token::value "(" (Token::value "12") ")") ";")"#);
+        }
+
+        it "matches character classes with whole tokens" {
+            assert!(volt.parse_tokens(&lex("let a = b;"), &entry).is_ok());
+            assert!(volt.parse_tokens(&lex("let a = bc;"), &entry).is_err());
+        }
+
+        it "uses token spans as positions" {
+            let tree = volt.parse_tokens(&lex("let a = 10;"), &entry).unwrap();
+            assert_eq!(tree.root.children.find_node("Token::value").span, span!(pos!(8, 0, 8), pos!(10, 0, 10)));
+
+            let tokens: Vec<Token> = lex("let a = ( 10 ) ;").into_iter().filter(|token| token.kind != "space").collect();
+            let tree = volt.parse_tokens(&tokens, &entry).unwrap();
+            let value = tree.root.children.find_node("Token::value");
+            assert_eq!(tree.root.span, span!(pos!(0, 0, 0), pos!(16, 0, 16)));
+            assert_eq!(value.span, span!(pos!(8, 0, 8), pos!(14, 0, 14)));
+            assert_eq!(value.children.find_node("Token::value").span, span!(pos!(10, 0, 10), pos!(12, 0, 12)));
+            assert_eq!(value.children.get_leaf(2).span, span!(pos!(13, 0, 13), pos!(14, 0, 14)));
+
+            assert_eq!(volt.parse_tokens(&lex("let a 1;"), &entry), Err(ParserError::UnexpectedInput {
+                position: pos!(6, 0, 6),
+                expected: vec![Expression::String("=".to_string())],
+            }));
+        }
+
+        it "reports expected token kind" {
+            assert_eq!(volt.parse_tokens(&lex("let 1"), &entry), Err(ParserError::UnexpectedInput {
+                position: pos!(4, 0, 4),
+                expected: vec![Expression::Token("word".to_string())],
+            }));
+        }
+
+        it "doesn't match token kinds in text" {
+            assert!(volt.parse("let a = 1;", &entry).is_err());
+        }
+
+        it "displays token expression" {
+            assert_eq!(token("word").to_string(), "<word>");
+        }
+    }
+}
@@ -33,4 +33,22 @@ speculate!{
             assert_eq!(left, right);
         }
     }
+
+    describe "syntax s-expression" {
+        it "formats tree into single line" {
+            let tree = tree!(
+                node!("root" => [
+                    node!("node" => [
+                        leaf!("a\"b"),
+                    ]),
+                    error!("msg", [
+                        leaf!("c"),
+                    ]),
+                    node!("empty" => []),
+                ])
+            );
+
+            assert_eq!(tree.to_sexp(), r#"(root (node "a\"b") (:error "msg" "c") (empty))"#);
+        }
+    }
 }
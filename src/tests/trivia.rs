@@ -0,0 +1,72 @@
+use {
+    crate::*,
+    crate::parser::*,
+    crate::tree::*,
+    speculate::speculate,
+};
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+
+        volt.add_grammar_str("Trivia", r#"
+            stmt := "let" name "=" value ";";
+            name := [a-z]+.join.lexical;
+            value := digits / "(" value ")";
+            digits := [0-9]+;
+            values := value+ ("," value)*;
+            spacing := (" " / "\n")*;
+        "#).unwrap();
+
+        volt.set_trivia("Trivia", RuleId("Trivia::spacing".to_string()));
+        #[allow(unused)]
+        let entry = RuleId("Trivia::stmt".to_string());
+    }
+
+    describe "trivia" {
+        it "skips trivia between elements of syntactic rules" {
+            let tree = volt.parse(" let ab = ( 12 )\n;\n", &entry).unwrap();
+            assert_eq!(tree.to_sexp(), r#"(Trivia::stmt "let" (Trivia::name "ab") "=" (Trivia::value "(" (Trivia::value (Trivia::digits "1" "2")) ")") ";")"#);
+        }
+
+        it "skips trivia between iterations of rules and sequences" {
+            let tree = volt.parse("1 2 , 3", &RuleId("Trivia::values".to_string())).unwrap();
+            assert_eq!(tree.to_sexp(), r#"(Trivia::values (Trivia::value (Trivia::digits "1")) (Trivia::value (Trivia::digits "2")) "," (Trivia::value (Trivia::digits "3")))"#);
+        }
+
+        it "doesn't skip trivia between iterations of character class" {
+            assert_eq!(volt.parse("let a = 1 2;", &entry), Err(ParserError::UnexpectedInput {
+                position: pos!(10, 0, 10),
+                expected: vec![Expression::String(";".to_string())],
+            }));
+        }
+
+        it "doesn't skip trivia inside lexical elements" {
+            assert_eq!(volt.parse("let a b = 1;", &entry), Err(ParserError::UnexpectedInput {
+                position: pos!(6, 0, 6),
+                expected: vec![Expression::String("=".to_string())],
+            }));
+        }
+
+        it "preserves trivia as hidden leaves" {
+            volt.set_trivia_preservation(true);
+            let tree = volt.parse(" let a =1;", &entry).unwrap();
+
+            assert_eq!(tree.to_sexp(), r#"(Trivia::stmt (:hidden " ") "let" (:hidden " ") (Trivia::name "a") (:hidden " ") "=" (Trivia::value (Trivia::digits "1")) ";")"#);
+            assert_eq!(tree.root.span, span!(pos!(0, 0, 0), pos!(10, 0, 10)));
+            assert_eq!(tree.root.children.get_node(1).name, "Trivia::name");
+            assert_eq!(SyntaxChild::Node(tree.root.clone()).join_children(), "leta=1;");
+        }
+
+        it "generates same tree with memoization" {
+            let input = "let ab = ((12));";
+            let expected = volt.parse(input, &entry);
+            volt.set_memoization(true);
+            assert_eq!(volt.parse(input, &entry), expected);
+        }
+
+        it "displays lexical element" {
+            assert_eq!(str("a").min(1).lexical().to_string(), "\"a\"+.lexical");
+        }
+    }
+}
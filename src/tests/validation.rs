@@ -0,0 +1,84 @@
+use {
+    // Use volt to resolve items in derive macro.
+    crate as volt,
+    crate::*,
+    crate::validation::*,
+    volt_derive::VoltModuleDefinition,
+    speculate::speculate,
+};
+
+speculate!{
+    before {
+        let volt = &mut Volt::new();
+        volt.add_module(ValidationModule::new());
+
+        #[allow(unused)]
+        let id = |name: &str| RuleId(format!("ValidationModule::{}", name));
+    }
+
+    describe "validation" {
+        it "reports no issue for valid rules" {
+            let volt = &mut Volt::new();
+            volt.add_grammar_str("Valid", r#"list := item ("," item)*; item := [a-z]+;"#).unwrap();
+            assert_eq!(volt.validate(&RuleId("Valid::list".to_string())), vec![]);
+        }
+
+        it "reports undefined entry rule" {
+            let volt = &mut Volt::new();
+            assert_eq!(volt.validate(&RuleId("Valid::list".to_string())), vec![
+                ValidationIssue::UndefinedRule { id: RuleId("Valid::list".to_string()), referenced_by: None },
+            ]);
+        }
+
+        it "reports issues in order" {
+            assert_eq!(volt.validate(&id("entry")), vec![
+                ValidationIssue::UndefinedRule { id: RuleId("Unknown::rule".to_string()), referenced_by: Some(id("undefined_reference")) },
+                ValidationIssue::UnreachableRule { id: id("unreachable") },
+                ValidationIssue::NullableLoop { id: id("nullable_loop"), element: "\"a\"?*".to_string() },
+                ValidationIssue::LeftRecursion { cycle: vec![id("left_recursion")] },
+            ]);
+        }
+
+        it "doesn't report bounded loop of nullable element" {
+            let volt = &mut Volt::new();
+            volt.add_grammar_str("Valid", r#"list := "a"?{0,3} (!"b" / "c")?+;"#).unwrap();
+
+            assert_eq!(volt.validate(&RuleId("Valid::list".to_string())), vec![
+                ValidationIssue::NullableLoop { id: RuleId("Valid::list".to_string()), element: "(!\"b\" / \"c\")?+".to_string() },
+            ]);
+        }
+
+        it "displays issues" {
+            assert_eq!(
+                ValidationIssue::UndefinedRule { id: RuleId("A::b".to_string()), referenced_by: Some(RuleId("A::a".to_string())) }.to_string(),
+                "rule `A::b` referenced by `A::a` is not defined",
+            );
+
+            assert_eq!(
+                ValidationIssue::LeftRecursion { cycle: vec![RuleId("A::a".to_string()), RuleId("A::b".to_string())] }.to_string(),
+                "rules `A::a`, `A::b` are left recursive",
+            );
+        }
+    }
+}
+
+#[derive(VoltModuleDefinition)]
+struct ValidationModule {
+    entry: Element,
+    undefined_reference: Element,
+    nullable_loop: Element,
+    left_recursion: Element,
+    unreachable: Element,
+}
+
+impl VoltModule for ValidationModule {
+    fn new() -> ValidationModule {
+        define_rules!{
+            entry := seq![ValidationModule::undefined_reference(), ValidationModule::nullable_loop(), ValidationModule::left_recursion()];
+            undefined_reference := choice![str("a"), Element::Expression(Expression::Rule(RuleId("Unknown::rule".to_string())))];
+            nullable_loop := str("a").optional().min(0);
+            left_recursion := choice![seq![ValidationModule::left_recursion(), str("a")], str("a")];
+            unreachable := str("a");
+        }
+    }
+}
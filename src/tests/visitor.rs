@@ -0,0 +1,179 @@
+use {
+    crate::*,
+    crate::tree::*,
+    crate::visitor::*,
+    speculate::speculate,
+};
+
+struct Recorder {
+    events: Vec<String>,
+    skipped_node: Option<&'static str>,
+    stopped_leaf: Option<&'static str>,
+}
+
+impl Recorder {
+    fn new() -> Recorder {
+        Recorder {
+            events: Vec::new(),
+            skipped_node: None,
+            stopped_leaf: None,
+        }
+    }
+}
+
+impl SyntaxVisitor for Recorder {
+    fn enter_node(&mut self, node: &SyntaxNode) -> VisitControl {
+        self.events.push(format!("enter {}", node.name));
+
+        if self.skipped_node == Some(node.name.as_str()) {
+            VisitControl::SkipChildren
+        } else {
+            VisitControl::Continue
+        }
+    }
+
+    fn leave_node(&mut self, node: &SyntaxNode) -> VisitControl {
+        self.events.push(format!("leave {}", node.name));
+        VisitControl::Continue
+    }
+
+    fn visit_leaf(&mut self, leaf: &SyntaxLeaf) -> VisitControl {
+        self.events.push(format!("leaf {}", leaf.value));
+
+        if self.stopped_leaf == Some(leaf.value.as_str()) {
+            VisitControl::Stop
+        } else {
+            VisitControl::Continue
+        }
+    }
+
+    fn visit_error(&mut self, error: &SyntaxError) -> VisitControl {
+        self.events.push(format!("error {}", error.message));
+        VisitControl::Continue
+    }
+}
+
+struct ErrorRemover;
+
+impl SyntaxVisitorMut for ErrorRemover {
+    fn edit_child(&mut self, child: &SyntaxChild) -> ChildEdit {
+        match child {
+            SyntaxChild::Error(_) => ChildEdit::Remove,
+            _ => ChildEdit::Keep,
+        }
+    }
+}
+
+struct GroupFlattener;
+
+impl SyntaxVisitorMut for GroupFlattener {
+    fn visit_leaf(&mut self, leaf: &mut SyntaxLeaf) -> VisitControl {
+        leaf.value = leaf.value.to_uppercase();
+        VisitControl::Continue
+    }
+
+    fn edit_child(&mut self, child: &SyntaxChild) -> ChildEdit {
+        match child {
+            SyntaxChild::Node(node) if node.name == "group" => ChildEdit::Replace(node.children.clone()),
+            _ => ChildEdit::Keep,
+        }
+    }
+}
+
+speculate!{
+    before {
+        let tree = tree!(
+            node!("root" => [
+                node!("group" => [
+                    leaf!("a"),
+                    leaf!("b"),
+                ]),
+                error!("msg", [
+                    leaf!("c"),
+                ]),
+                leaf!("d"),
+            ])
+        );
+    }
+
+    describe "syntax visitor" {
+        it "visits children in order" {
+            let mut recorder = Recorder::new();
+            tree.walk(&mut recorder);
+
+            assert_eq!(recorder.events, vec![
+                "enter root",
+                "enter group",
+                "leaf a",
+                "leaf b",
+                "leave group",
+                "error msg",
+                "leaf c",
+                "leaf d",
+                "leave root",
+            ]);
+        }
+
+        it "skips children of node" {
+            let mut recorder = Recorder::new();
+            recorder.skipped_node = Some("group");
+            tree.walk(&mut recorder);
+
+            assert_eq!(recorder.events, vec![
+                "enter root",
+                "enter group",
+                "leave group",
+                "error msg",
+                "leaf c",
+                "leaf d",
+                "leave root",
+            ]);
+        }
+
+        it "stops traversal" {
+            let mut recorder = Recorder::new();
+            recorder.stopped_leaf = Some("b");
+            tree.walk(&mut recorder);
+
+            assert_eq!(recorder.events, vec![
+                "enter root",
+                "enter group",
+                "leaf a",
+                "leaf b",
+            ]);
+        }
+    }
+
+    describe "mutable syntax visitor" {
+        it "removes children" {
+            let mut tree = tree.clone();
+            tree.walk_mut(&mut ErrorRemover);
+
+            assert_eq!(tree, tree!(
+                node!("root" => [
+                    node!("group" => [
+                        leaf!("a"),
+                        leaf!("b"),
+                    ]),
+                    leaf!("d"),
+                ])
+            ));
+        }
+
+        it "replaces children" {
+            let mut tree = tree.clone();
+            tree.walk_mut(&mut GroupFlattener);
+
+            assert_eq!(tree, tree!(
+                node!("root" => [
+                    leaf!("A"),
+                    leaf!("B"),
+                    error!("msg", [
+                        leaf!("C"),
+                    ]),
+                    leaf!("D"),
+                ])
+            ));
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use std::fmt;
-
-use crate::parser::ParserInput;
+use {
+    std::fmt,
+    crate::element::escape,
+};
 
 #[macro_export]
 macro_rules! tree {
@@ -15,25 +16,25 @@ macro_rules! tree {
 #[macro_export]
 macro_rules! node {
     ($name:expr => [$($child:expr),* $(,)?]) => {
-        SyntaxChild::node($name.to_string(), vec![$($child),*])
+        SyntaxChild::node($name.to_string(), span!(), vec![$($child),*])
     };
 }
 
 #[macro_export]
 macro_rules! leaf {
     ($start:expr, $value:expr) => {
-        SyntaxChild::leaf($start, $value.to_string())
+        SyntaxChild::leaf(span!($start, pos!()), $value.to_string())
     };
 
     ($value:expr) => {
-        SyntaxChild::leaf(pos!(usize::MAX, usize::MAX, usize::MAX), $value.to_string())
+        SyntaxChild::leaf(span!(), $value.to_string())
     };
 }
 
 #[macro_export]
 macro_rules! error {
     ($message:expr, [$($child:expr),* $(,)?]) => {
-        SyntaxChild::error($message.to_string(), vec![$($child),*])
+        SyntaxChild::error($message.to_string(), span!(), vec![$($child),*])
     };
 }
 
@@ -42,6 +43,21 @@ macro_rules! pos {
     ($index:expr, $line:expr, $column:expr) => {
         InputPosition::new($index, $line, $column)
     };
+
+    () => {
+        InputPosition::new(usize::MAX, usize::MAX, usize::MAX)
+    };
+}
+
+#[macro_export]
+macro_rules! span {
+    ($start:expr, $end:expr) => {
+        Span::new($start, $end)
+    };
+
+    () => {
+        Span::new(pos!(), pos!())
+    };
 }
 
 pub trait SyntaxDisplay {
@@ -60,7 +76,17 @@ impl fmt::Display for SyntaxDisplayLine {
     }
 }
 
+// Formats syntax into a single-line S-expression.
+pub trait SyntaxSexp {
+    fn to_sexp(&self) -> String;
+}
+
+fn children_to_sexp(children: &[SyntaxChild]) -> String {
+    children.iter().map(|each_child| format!(" {}", each_child.to_sexp())).collect()
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SyntaxTree {
     pub root: SyntaxNode,
 }
@@ -71,19 +97,30 @@ impl SyntaxDisplay for SyntaxTree {
     }
 }
 
+impl SyntaxSexp for SyntaxTree {
+    fn to_sexp(&self) -> String {
+        self.root.to_sexp()
+    }
+}
+
 impl SyntaxTree {
     pub fn new(root: SyntaxNode) -> SyntaxTree {
         SyntaxTree {
             root,
         }
     }
+
+    pub fn to_source(&self) -> String {
+        self.root.children.iter().map(|child| child.to_source()).collect()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
 pub enum SyntaxChild {
     Node(SyntaxNode),
     Leaf(SyntaxLeaf),
-    // todo: add position
     Error(SyntaxError),
 }
 
@@ -97,17 +134,43 @@ impl SyntaxDisplay for SyntaxChild {
     }
 }
 
+impl SyntaxSexp for SyntaxChild {
+    fn to_sexp(&self) -> String {
+        match self {
+            SyntaxChild::Node(node) => node.to_sexp(),
+            SyntaxChild::Leaf(leaf) => leaf.to_sexp(),
+            SyntaxChild::Error(error) => error.to_sexp(),
+        }
+    }
+}
+
 impl SyntaxChild {
-    pub fn node(name: String, children: Vec<SyntaxChild>) -> SyntaxChild {
-        SyntaxChild::Node(SyntaxNode::new(name, children))
+    pub fn node(name: String, span: Span, children: Vec<SyntaxChild>) -> SyntaxChild {
+        SyntaxChild::Node(SyntaxNode::new(name, span, children))
     }
 
-    pub fn leaf(start: InputPosition, value: String) -> SyntaxChild {
-        SyntaxChild::Leaf(SyntaxLeaf::new(start, value))
+    pub fn leaf(span: Span, value: String) -> SyntaxChild {
+        SyntaxChild::Leaf(SyntaxLeaf::new(span, value))
     }
 
-    pub fn error(message: String, children: Vec<SyntaxChild>) -> SyntaxChild {
-        SyntaxChild::Error(SyntaxError::new(message, children))
+    pub fn error(message: String, span: Span, children: Vec<SyntaxChild>) -> SyntaxChild {
+        SyntaxChild::Error(SyntaxError::new(message, span, children))
+    }
+
+    pub fn hidden_leaf(span: Span, value: String) -> SyntaxChild {
+        SyntaxChild::Leaf(SyntaxLeaf::hidden(span, value))
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, SyntaxChild::Leaf(leaf) if leaf.hidden)
+    }
+
+    pub fn span(&self) -> &Span {
+        match self {
+            SyntaxChild::Node(node) => &node.span,
+            SyntaxChild::Leaf(leaf) => &leaf.span,
+            SyntaxChild::Error(error) => &error.span,
+        }
     }
 
     pub fn into_node(&self) -> &SyntaxNode {
@@ -134,6 +197,15 @@ impl SyntaxChild {
         }
     }
 
+    // Concatenates all leaves including hidden ones and ones inside errors.
+    pub fn to_source(&self) -> String {
+        match self {
+            SyntaxChild::Node(node) => node.children.iter().map(|child| child.to_source()).collect(),
+            SyntaxChild::Leaf(leaf) => leaf.to_source(),
+            SyntaxChild::Error(error) => error.children.iter().map(|child| child.to_source()).collect(),
+        }
+    }
+
     pub fn join_children(&self) -> String {
         let mut s = String::new();
 
@@ -141,18 +213,39 @@ impl SyntaxChild {
             SyntaxChild::Node(node) => for each_child in &node.children {
                 s += &each_child.join_children()
             },
-            SyntaxChild::Leaf(leaf) => s += &leaf.value,
-            SyntaxChild::Error(_) => (),
+            SyntaxChild::Leaf(leaf) if !leaf.hidden => s += &leaf.value,
+            _ => (),
         }
 
         s
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SyntaxNode {
     pub name: String,
+    pub span: Span,
     pub children: Vec<SyntaxChild>,
+    // Set on rule nodes by the parser, and ignored by comparison.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) application: Option<RuleApplication>,
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.span == other.span && self.children == other.children
+    }
+}
+
+// Conditions under which a rule node can be reused by incremental reparsing. Indexes are byte offsets unlike spans.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RuleApplication {
+    pub(crate) start_index: usize,
+    pub(crate) end_index: usize,
+    // Exclusive end of examined input. It exceeds the input length when the end of input is examined.
+    pub(crate) examined_index: usize,
+    pub(crate) lexical: bool,
 }
 
 impl SyntaxDisplay for SyntaxNode {
@@ -174,44 +267,117 @@ impl SyntaxDisplay for SyntaxNode {
     }
 }
 
+impl SyntaxSexp for SyntaxNode {
+    fn to_sexp(&self) -> String {
+        format!("({}{})", self.name, children_to_sexp(&self.children))
+    }
+}
+
 impl SyntaxNode {
-    pub fn new(name: String, children: Vec<SyntaxChild>) -> SyntaxNode {
+    pub fn new(name: String, span: Span, children: Vec<SyntaxChild>) -> SyntaxNode {
         SyntaxNode {
             name,
+            span,
             children,
+            application: None,
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SyntaxLeaf {
-    pub start: InputPosition,
+    pub span: Span,
     pub value: String,
+    // Hidden leaves such as preserved trivia are ignored by index access and joining.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "std::ops::Not::not"))]
+    pub hidden: bool,
+    // Hidden input before and after the leaf in lossless mode. It's out of the span.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "String::is_empty"))]
+    pub leading_trivia: String,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "String::is_empty"))]
+    pub trailing_trivia: String,
+    // Input of the leaf if it differs from the value, e.g. joined leaves over hidden input in lossless mode.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub source: Option<String>,
 }
 
 impl SyntaxDisplay for SyntaxLeaf {
     fn fmt(&self, indent: usize) -> Vec<SyntaxDisplayLine> {
+        let mut text = if self.hidden {
+            format!("[HIDDEN] \"{}\"", self.value)
+        } else {
+            format!("\"{}\"", self.value)
+        };
+
+        for (label, trivia) in self.trivia_parts() {
+            text += &format!(" [{}] \"{}\"", label.to_uppercase(), trivia);
+        }
+
         vec![
             SyntaxDisplayLine {
                 indent,
-                text: format!("\"{}\"", self.value),
+                text,
             },
         ]
     }
 }
 
+impl SyntaxSexp for SyntaxLeaf {
+    fn to_sexp(&self) -> String {
+        let trivia_parts = self.trivia_parts();
+
+        if self.hidden {
+            format!("(:hidden \"{}\")", escape(&self.value))
+        } else if trivia_parts.is_empty() {
+            format!("\"{}\"", escape(&self.value))
+        } else {
+            let trivia_parts = trivia_parts.iter().map(|(label, trivia)| format!(" :{} \"{}\"", label, escape(trivia))).collect::<String>();
+            format!("(:leaf \"{}\"{})", escape(&self.value), trivia_parts)
+        }
+    }
+}
+
 impl SyntaxLeaf {
-    pub fn new(start: InputPosition, value: String) -> SyntaxLeaf {
+    pub fn new(span: Span, value: String) -> SyntaxLeaf {
         SyntaxLeaf {
-            start,
+            span,
+            value,
+            hidden: false,
+            leading_trivia: String::new(),
+            trailing_trivia: String::new(),
+            source: None,
+        }
+    }
+
+    pub fn hidden(span: Span, value: String) -> SyntaxLeaf {
+        SyntaxLeaf {
+            span,
             value,
+            hidden: true,
+            leading_trivia: String::new(),
+            trailing_trivia: String::new(),
+            source: None,
         }
     }
+
+    // Input of the leaf with its trivia.
+    pub fn to_source(&self) -> String {
+        format!("{}{}{}", self.leading_trivia, self.source.as_ref().unwrap_or(&self.value), self.trailing_trivia)
+    }
+
+    // Labels and texts of non-empty trivia and source in the input order.
+    fn trivia_parts(&self) -> Vec<(&str, &str)> {
+        let parts = [("leading", Some(&self.leading_trivia)), ("source", self.source.as_ref()), ("trailing", Some(&self.trailing_trivia))];
+        parts.into_iter().filter_map(|(label, text)| text.filter(|text| !text.is_empty()).map(|text| (label, text.as_str()))).collect()
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SyntaxError {
     pub message: String,
+    pub span: Span,
     pub children: Vec<SyntaxChild>,
 }
 
@@ -234,10 +400,18 @@ impl SyntaxDisplay for SyntaxError {
     }
 }
 
+// Errors are distinguished from nodes by the leading colon.
+impl SyntaxSexp for SyntaxError {
+    fn to_sexp(&self) -> String {
+        format!("(:error \"{}\"{})", escape(&self.message), children_to_sexp(&self.children))
+    }
+}
+
 impl SyntaxError {
-    pub fn new(message: String, children: Vec<SyntaxChild>) -> SyntaxError {
+    pub fn new(message: String, span: Span, children: Vec<SyntaxChild>) -> SyntaxError {
         SyntaxError {
             message,
+            span,
             children,
         }
     }
@@ -245,42 +419,44 @@ impl SyntaxError {
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct InputPositionCounter {
-    pub(crate) lines: Vec<(usize, usize)>,
+    // Byte offsets of line starts.
+    pub(crate) lines: Vec<usize>,
+    // Byte offsets after multibyte characters and the numbers of extra bytes until there.
+    pub(crate) multibyte_chars: Vec<(usize, usize)>,
 }
 
 impl From<&str> for InputPositionCounter {
     fn from(input: &str) -> InputPositionCounter {
-        let mut lines = Vec::new();
-        let mut latest_line_start = 0;
+        let mut lines = vec![0];
+        let mut multibyte_chars = Vec::new();
+        let mut extra_bytes = 0;
+
+        for (offset, ch) in input.char_indices() {
+            let end = offset + ch.len_utf8();
+
+            if ch.len_utf8() > 1 {
+                extra_bytes += ch.len_utf8() - 1;
+                multibyte_chars.push((end, extra_bytes));
+            }
 
-        for (i, ch) in input.chars().enumerate() {
             if ch == '\n' {
-                let new_line_start = i + 1;
-                lines.push((latest_line_start, new_line_start - latest_line_start));
-                latest_line_start = new_line_start;
+                lines.push(end);
             }
         }
 
-        lines.push((latest_line_start, input.count() - latest_line_start));
-
         InputPositionCounter {
             lines,
+            multibyte_chars,
         }
     }
 }
 
 impl InputPositionCounter {
-    pub fn get_position(&self, index: usize) -> InputPosition {
-        let mut line = 0;
-        let mut column = 0;
-
-        for (each_line, (each_line_start, each_line_len)) in self.lines.iter().enumerate() {
-            if index < each_line_start + each_line_len {
-                line = each_line;
-                column = index - each_line_start;
-                break;
-            }
-        }
+    // Converts a byte offset into a position counted in characters.
+    pub fn get_position(&self, offset: usize) -> InputPosition {
+        let line = self.lines.partition_point(|line_start| *line_start <= offset) - 1;
+        let index = self.get_char_index(offset);
+        let column = index - self.get_char_index(self.lines[line]);
 
         InputPosition {
             index,
@@ -288,9 +464,25 @@ impl InputPositionCounter {
             column,
         }
     }
+
+    // Converts an index counted in characters into a byte offset.
+    pub fn get_offset(&self, index: usize) -> usize {
+        match self.multibyte_chars.partition_point(|(end, extra_bytes)| end - extra_bytes <= index) {
+            0 => index,
+            i => index + self.multibyte_chars[i - 1].1,
+        }
+    }
+
+    fn get_char_index(&self, offset: usize) -> usize {
+        match self.multibyte_chars.partition_point(|(end, _)| *end <= offset) {
+            0 => offset,
+            i => offset - self.multibyte_chars[i - 1].1,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputPosition {
     pub index: usize,
     pub line: usize,
@@ -308,6 +500,13 @@ impl PartialEq for InputPosition {
     }
 }
 
+// Lines and columns are displayed one-based.
+impl fmt::Display for InputPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column + 1)
+    }
+}
+
 impl InputPosition {
     pub fn new(index: usize, line: usize, column: usize) -> InputPosition {
         InputPosition {
@@ -318,15 +517,37 @@ impl InputPosition {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: InputPosition,
+    pub end: InputPosition,
+}
+
+impl Span {
+    pub fn new(start: InputPosition, end: InputPosition) -> Span {
+        Span {
+            start,
+            end,
+        }
+    }
+}
+
 pub trait SyntaxChildVec {
     fn expand(self, hierarchy: usize, recursive: bool) -> Vec<SyntaxChild>;
 
     fn get_start_position(&self) -> Option<InputPosition>;
 
+    fn get_end_position(&self) -> Option<InputPosition>;
+
+    fn get_span(&self) -> Option<Span>;
+
     fn eject_errors(self) -> Vec<SyntaxChild>;
 
     fn join_into_string(&self) -> String;
 
+    fn join_into_source(&self) -> String;
+
     fn get_child(&self, index: usize) -> &SyntaxChild;
 
     fn get_child_or_none(&self, index: usize) -> Option<&SyntaxChild>;
@@ -371,7 +592,7 @@ impl SyntaxChildVec for Vec<SyntaxChild> {
                 SyntaxChild::Node(node) => if let Some(v) = node.children.get_start_position() {
                     return Some(v);
                 },
-                SyntaxChild::Leaf(leaf) => return Some(leaf.start.clone()),
+                SyntaxChild::Leaf(leaf) => return Some(leaf.span.start.clone()),
                 SyntaxChild::Error(err) => if let Some(v) = err.children.get_start_position() {
                     return Some(v);
                 },
@@ -381,6 +602,26 @@ impl SyntaxChildVec for Vec<SyntaxChild> {
         None
     }
 
+    fn get_end_position(&self) -> Option<InputPosition> {
+        for each_child in self.iter().rev() {
+            match each_child {
+                SyntaxChild::Node(node) => if let Some(v) = node.children.get_end_position() {
+                    return Some(v);
+                },
+                SyntaxChild::Leaf(leaf) => return Some(leaf.span.end.clone()),
+                SyntaxChild::Error(err) => if let Some(v) = err.children.get_end_position() {
+                    return Some(v);
+                },
+            }
+        }
+
+        None
+    }
+
+    fn get_span(&self) -> Option<Span> {
+        Some(Span::new(self.get_start_position()?, self.get_end_position()?))
+    }
+
     fn eject_errors(self) -> Vec<SyntaxChild> {
         let mut errors = Vec::new();
 
@@ -401,14 +642,29 @@ impl SyntaxChildVec for Vec<SyntaxChild> {
         for each_child in self {
             match each_child {
                 SyntaxChild::Node(node) => value += &node.children.join_into_string(),
-                SyntaxChild::Leaf(leaf) => value += &leaf.value,
-                SyntaxChild::Error(_) => (),
+                SyntaxChild::Leaf(leaf) if !leaf.hidden => value += &leaf.value,
+                _ => (),
             }
         }
 
         value
     }
 
+    // Joins input of leaves including hidden ones and trivia. Errors are excluded as they're ejected by joining.
+    fn join_into_source(&self) -> String {
+        let mut source = String::new();
+
+        for each_child in self {
+            match each_child {
+                SyntaxChild::Node(node) => source += &node.children.join_into_source(),
+                SyntaxChild::Leaf(leaf) => source += &leaf.to_source(),
+                SyntaxChild::Error(_) => (),
+            }
+        }
+
+        source
+    }
+
     fn get_child(&self, index: usize) -> &SyntaxChild {
         if let Some(child) = self.get_child_or_none(index) {
             child
@@ -418,7 +674,7 @@ impl SyntaxChildVec for Vec<SyntaxChild> {
     }
 
     fn get_child_or_none(&self, index: usize) -> Option<&SyntaxChild> {
-        self.get(index)
+        self.iter().filter(|child| !child.is_hidden()).nth(index)
     }
 
     fn get_node(&self, index: usize) -> &SyntaxNode {
@@ -0,0 +1,98 @@
+use {
+    std::collections::HashSet,
+    std::fmt::{self, Display, Formatter},
+    crate::{
+        analysis::*,
+        rule::RuleId,
+    },
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationIssue {
+    // `referenced_by` is None when the entry rule itself is undefined.
+    UndefinedRule { id: RuleId, referenced_by: Option<RuleId> },
+    UnreachableRule { id: RuleId },
+    NullableLoop { id: RuleId, element: String },
+    // Left recursion is supported by the parser but reported since it changes the parsing cost.
+    LeftRecursion { cycle: Vec<RuleId> },
+}
+
+impl Display for ValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::UndefinedRule { id, referenced_by: Some(referenced_by) } => write!(f, "rule `{}` referenced by `{}` is not defined", id, referenced_by),
+            ValidationIssue::UndefinedRule { id, referenced_by: None } => write!(f, "entry rule `{}` is not defined", id),
+            ValidationIssue::UnreachableRule { id } => write!(f, "rule `{}` is unreachable from the entry rule", id),
+            ValidationIssue::NullableLoop { id, element } => write!(f, "loop `{}` in rule `{}` can match empty input", element, id),
+            ValidationIssue::LeftRecursion { cycle } => write!(f, "rules {} are left recursive", cycle.iter().map(|id| format!("`{}`", id)).collect::<Vec<String>>().join(", ")),
+        }
+    }
+}
+
+// Trivia rules and rules of recovery elements are reachable since the parser calls them implicitly.
+pub(crate) fn validate(rule_map: &RuleMap, entry_rule_id: &RuleId, implicit_rule_ids: &[RuleId]) -> Vec<ValidationIssue> {
+    let mut ids: Vec<&RuleId> = rule_map.keys().collect();
+    ids.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut issues = Vec::new();
+
+    if !rule_map.contains_key(entry_rule_id) {
+        issues.push(ValidationIssue::UndefinedRule { id: entry_rule_id.clone(), referenced_by: None });
+    }
+
+    for each_id in &ids {
+        let mut references = Vec::new();
+        rule_map[*each_id].rule_ids(&mut references);
+
+        for each_reference in references {
+            if !rule_map.contains_key(&each_reference) {
+                issues.push(ValidationIssue::UndefinedRule { id: each_reference, referenced_by: Some((*each_id).clone()) });
+            }
+        }
+    }
+
+    let mut roots = vec![entry_rule_id.clone()];
+    roots.extend(implicit_rule_ids.iter().cloned());
+    let reachable = reachable_rules(rule_map, roots);
+
+    for each_id in &ids {
+        if !reachable.contains(*each_id) {
+            issues.push(ValidationIssue::UnreachableRule { id: (*each_id).clone() });
+        }
+    }
+
+    let nullable = nullable_rules(rule_map);
+
+    for each_id in &ids {
+        let mut loops = Vec::new();
+        rule_map[*each_id].nullable_loops(&nullable, &mut loops);
+
+        for each_loop in loops {
+            issues.push(ValidationIssue::NullableLoop { id: (*each_id).clone(), element: each_loop.to_string() });
+        }
+    }
+
+    let mut cycles = left_recursive_cycles(&leftmost_graph(rule_map, &nullable));
+    cycles.sort_by(|a, b| a[0].0.cmp(&b[0].0));
+
+    for each_cycle in cycles {
+        issues.push(ValidationIssue::LeftRecursion { cycle: each_cycle });
+    }
+
+    issues
+}
+
+fn reachable_rules(rule_map: &RuleMap, roots: Vec<RuleId>) -> HashSet<RuleId> {
+    let mut reachable = HashSet::new();
+    let mut stack = roots;
+
+    while let Some(id) = stack.pop() {
+        if let Some(elem) = rule_map.get(&id) {
+            if reachable.insert(id) {
+                elem.rule_ids(&mut stack);
+            }
+        }
+    }
+
+    reachable
+}
@@ -0,0 +1,154 @@
+use crate::tree::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VisitControl {
+    Continue,
+    SkipChildren,
+    Stop,
+}
+
+pub trait SyntaxVisitor {
+    fn enter_node(&mut self, _node: &SyntaxNode) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn leave_node(&mut self, _node: &SyntaxNode) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_leaf(&mut self, _leaf: &SyntaxLeaf) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    // Children of the error are visited unless skipped.
+    fn visit_error(&mut self, _error: &SyntaxError) -> VisitControl {
+        VisitControl::Continue
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChildEdit {
+    Keep,
+    Remove,
+    Replace(Vec<SyntaxChild>),
+}
+
+pub trait SyntaxVisitorMut {
+    fn enter_node(&mut self, _node: &mut SyntaxNode) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn leave_node(&mut self, _node: &mut SyntaxNode) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_leaf(&mut self, _leaf: &mut SyntaxLeaf) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    fn visit_error(&mut self, _error: &mut SyntaxError) -> VisitControl {
+        VisitControl::Continue
+    }
+
+    // Called after each child is visited. Replacing children aren't visited.
+    fn edit_child(&mut self, _child: &SyntaxChild) -> ChildEdit {
+        ChildEdit::Keep
+    }
+}
+
+impl SyntaxTree {
+    pub fn walk<V: SyntaxVisitor + ?Sized>(&self, visitor: &mut V) {
+        walk_node(&self.root, visitor);
+    }
+
+    pub fn walk_mut<V: SyntaxVisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_node_mut(&mut self.root, visitor);
+    }
+}
+
+impl SyntaxChild {
+    pub fn walk<V: SyntaxVisitor + ?Sized>(&self, visitor: &mut V) {
+        walk_child(self, visitor);
+    }
+
+    pub fn walk_mut<V: SyntaxVisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_child_mut(self, visitor);
+    }
+}
+
+// Each function returns false when the traversal is stopped.
+fn walk_child<V: SyntaxVisitor + ?Sized>(child: &SyntaxChild, visitor: &mut V) -> bool {
+    match child {
+        SyntaxChild::Node(node) => walk_node(node, visitor),
+        SyntaxChild::Leaf(leaf) => visitor.visit_leaf(leaf) != VisitControl::Stop,
+        SyntaxChild::Error(error) => match visitor.visit_error(error) {
+            VisitControl::Continue => walk_children(&error.children, visitor),
+            VisitControl::SkipChildren => true,
+            VisitControl::Stop => false,
+        },
+    }
+}
+
+fn walk_node<V: SyntaxVisitor + ?Sized>(node: &SyntaxNode, visitor: &mut V) -> bool {
+    match visitor.enter_node(node) {
+        VisitControl::Continue => if !walk_children(&node.children, visitor) {
+            return false;
+        },
+        VisitControl::SkipChildren => (),
+        VisitControl::Stop => return false,
+    }
+
+    visitor.leave_node(node) != VisitControl::Stop
+}
+
+fn walk_children<V: SyntaxVisitor + ?Sized>(children: &[SyntaxChild], visitor: &mut V) -> bool {
+    children.iter().all(|each_child| walk_child(each_child, visitor))
+}
+
+fn walk_child_mut<V: SyntaxVisitorMut + ?Sized>(child: &mut SyntaxChild, visitor: &mut V) -> bool {
+    match child {
+        SyntaxChild::Node(node) => walk_node_mut(node, visitor),
+        SyntaxChild::Leaf(leaf) => visitor.visit_leaf(leaf) != VisitControl::Stop,
+        SyntaxChild::Error(error) => match visitor.visit_error(error) {
+            VisitControl::Continue => walk_children_mut(&mut error.children, visitor),
+            VisitControl::SkipChildren => true,
+            VisitControl::Stop => false,
+        },
+    }
+}
+
+fn walk_node_mut<V: SyntaxVisitorMut + ?Sized>(node: &mut SyntaxNode, visitor: &mut V) -> bool {
+    match visitor.enter_node(node) {
+        VisitControl::Continue => if !walk_children_mut(&mut node.children, visitor) {
+            return false;
+        },
+        VisitControl::SkipChildren => (),
+        VisitControl::Stop => return false,
+    }
+
+    visitor.leave_node(node) != VisitControl::Stop
+}
+
+fn walk_children_mut<V: SyntaxVisitorMut + ?Sized>(children: &mut Vec<SyntaxChild>, visitor: &mut V) -> bool {
+    let mut i = 0;
+
+    while i < children.len() {
+        if !walk_child_mut(&mut children[i], visitor) {
+            return false;
+        }
+
+        match visitor.edit_child(&children[i]) {
+            ChildEdit::Keep => i += 1,
+            ChildEdit::Remove => {
+                children.remove(i);
+            },
+            ChildEdit::Replace(new_children) => {
+                let len = new_children.len();
+                children.splice(i..i + 1, new_children);
+                i += len;
+            },
+        }
+    }
+
+    true
+}